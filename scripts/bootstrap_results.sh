@@ -0,0 +1,31 @@
+#!/bin/sh
+
+# Run this after process_data has written the epoch data into data_processed, and optionally split the epochs into
+# blocks of slots in data_blocks (see process_data.sh)
+
+# Arguments are SAMPLES SEED followed by a single credit policy (see README.txt), e.g.:
+# bootstrap_results.sh 1000 1 agave
+
+# Writes confidence intervals over all epochs into "bootstrap"
+
+SAMPLES=$1
+SEED=$2
+POLICY=$3
+
+if [ -z "$SAMPLES" -o -z "$SEED" -o -z "$POLICY" ]; then
+    echo "Usage: bootstrap_results.sh <samples> <seed> <credit_policy>"
+    exit -1
+fi
+
+# Epochs that were split into blocks are resampled by block as well
+EPOCHS=$(for i in $(cd data_processed; ls); do
+    if [ -d data_blocks/$i ]; then echo -n "data_blocks/$i "; else echo -n "data_processed/$i "; fi
+done)
+
+DIR=$(echo -n "$POLICY" | tr ':,=/' '____')
+
+mkdir -p bootstrap
+
+./target/release/calculate_results v $POLICY bootstrap $SAMPLES $SEED $EPOCHS > bootstrap/v_$DIR
+
+./target/release/calculate_results d $POLICY bootstrap $SAMPLES $SEED $EPOCHS > bootstrap/d_$DIR
@@ -2,31 +2,23 @@
 
 # Run this after process_data has written the epoch data into data_processed
 
-# Arguments are GRACE MAX_CREDITS MULTIPLIER
+# Arguments are GRACE MAX_CREDITS MULTIPLIER, or a single credit policy, e.g. "agave" or "exponential:2:16:0.8" (see
+# README.txt)
 
-# Writes results into "results"
+# Set NORMALIZE=theoretical to give pcts relative to the maximum credits achievable in each epoch (which requires the
+# slot metadata in data_slots) rather than relative to the best validator or data center
 
-GRACE_PERIOD=$1
-MAX_CREDITS=$2
-REDUCTION_FACTOR=$3
+# Writes results into "results", in a directory named after the policy, skipping epochs whose results are up to date;
+# set FORCE=1 to calculate them again (see "pipeline calculate" in README.txt)
 
-if [ -z "$GRACE_PERIOD" -o -z "$MAX_CREDITS" -o -z "$REDUCTION_FACTOR" ]; then
+if [ -z "$1" ]; then
     echo "Usage: calculate_results.sh <grace_period> <max_credits> <reduction_factor>"
+    echo "       calculate_results.sh <credit_policy>"
     exit -1
 fi
 
-EPOCHS=$(for i in $(cd data_processed; ls); do echo -n "$i "; done)
-
-mkdir -p results
-
-DIR="${GRACE_PERIOD}_${MAX_CREDITS}_${REDUCTION_FACTOR}"
-
-mkdir -p results/$DIR
-
-for epoch in $EPOCHS; do
-
-    ./target/release/calculate_results v $GRACE_PERIOD $MAX_CREDITS $REDUCTION_FACTOR < data_processed/$epoch > results/$DIR/v_$epoch
-    
-    ./target/release/calculate_results d $GRACE_PERIOD $MAX_CREDITS $REDUCTION_FACTOR < data_processed/$epoch > results/$DIR/d_$epoch
-    
-done
+if [ "$NORMALIZE" = "theoretical" ]; then
+    ./target/release/pipeline ${FORCE:+--force} calculate "$@" theoretical
+else
+    ./target/release/pipeline ${FORCE:+--force} calculate "$@"
+fi
@@ -0,0 +1,40 @@
+#!/bin/sh
+
+# Run this after process_data has written the epoch data into data_processed, for epochs which have stake and
+# commission files (see fetch_stakes.sh)
+
+# Arguments are REWARD_POOL followed by GRACE MAX_CREDITS MULTIPLIER, or by a single credit policy (see README.txt)
+
+# Writes rewards into "rewards"
+
+REWARD_POOL=$1
+
+if [ -z "$REWARD_POOL" -o -z "$2" ]; then
+    echo "Usage: calculate_rewards.sh <reward_pool> <grace_period> <max_credits> <reduction_factor>"
+    echo "       calculate_rewards.sh <reward_pool> <credit_policy>"
+    exit -1
+fi
+
+shift
+
+if [ -z "$2" ]; then
+    POLICY=$1
+    DIR=$(echo -n "$POLICY" | tr ':,=/' '____')
+else
+    POLICY="$1 $2 $3"
+    DIR="${1}_${2}_${3}"
+fi
+
+EPOCHS=$(for i in $(cd data_processed; ls); do if [ -f data_commissions/$i ]; then echo -n "$i "; fi; done)
+
+mkdir -p rewards/$DIR
+
+for epoch in $EPOCHS; do
+
+    ./target/release/calculate_results v $POLICY rewards $REWARD_POOL data_commissions/$epoch < data_processed/$epoch \
+        > rewards/$DIR/v_$epoch
+
+    ./target/release/calculate_results d $POLICY rewards $REWARD_POOL data_commissions/$epoch < data_processed/$epoch \
+        > rewards/$DIR/d_$epoch
+
+done
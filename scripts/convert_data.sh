@@ -0,0 +1,18 @@
+#!/bin/sh
+
+# Run this after fetch_data.sh, to convert the fetched data into binary vote streams, which are much faster for
+# process_data to read (see README.txt)
+
+# Argument is optional cluster name, recorded in each vote stream (default "mainnet-beta")
+
+# Writes data/EPOCH.votes beside each data/EPOCH.gz that doesn't have one yet; process_data.sh then reads those instead
+
+CLUSTER=${1:-mainnet-beta}
+
+for gz in data/*.gz; do
+    epoch=$(basename $gz .gz)
+    if [ -f data/$epoch.votes ]; then
+        continue
+    fi
+    ./target/release/convert_votes $gz data/$epoch.votes "$CLUSTER" || exit -1
+done
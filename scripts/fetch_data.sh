@@ -2,27 +2,26 @@
 
 # Run this first to the data from google bigtables
 
-# Arguments are: credentials_path first_epoch last_epoch
+# Arguments are: credentials_path first_epoch last_epoch [epoch_schedule]
 
-# Writes data into a directory 'data'
+# epoch_schedule defaults to "mainnet"; see src/main.rs for the other forms it can take
+
+# Writes data into a directory 'data', slot metadata into a directory 'data_slots', and a report of the blocks that
+# could not be fetched into a directory 'data_gaps'
+
+# Epochs are fetched in order, each one starting from the vote account state saved at the end of the previous epoch
+# (data/EPOCH.state), so that votes which landed in the previous epoch are not counted again.  An epoch that was
+# interrupted part way through is resumed from its checkpoint when this script is run again, and epochs that were
+# already completely fetched are skipped (see "pipeline fetch" in README.txt)
 
 CREDENTIALS_PATH=$1
 FIRST_EPOCH=$2
 LAST_EPOCH=$3
+EPOCH_SCHEDULE=${4:-mainnet}
 
 if [ -z "$CREDENTIALS_PATH" -o -z "$FIRST_EPOCH" -o -z "$LAST_EPOCH" ]; then
-    echo "Usage: fetch_data.sh <credendials_path> <first_epoch> <last_epoch>"
+    echo "Usage: fetch_data.sh <credendials_path> <first_epoch> <last_epoch> [<epoch_schedule>]"
     exit -1
 fi
 
-mkdir -p data
-
-C=0
-for epoch in `seq $FIRST_EPOCH $LAST_EPOCH`; do
-    (./target/release/fetch_data "$CREDENTIALS_PATH" $(($epoch*432000)) $(($(($(($epoch+1))*432000))-1)) | gzip -c > data/$epoch.gz) &
-    pids[${C}]=$!
-done
-
-for pid in ${pids[*]}; do
-    wait $pid
-done
+./target/release/pipeline fetch "$CREDENTIALS_PATH" $FIRST_EPOCH $LAST_EPOCH "$EPOCH_SCHEDULE"
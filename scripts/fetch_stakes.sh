@@ -0,0 +1,29 @@
+#!/bin/sh
+
+# Fetch the activated stake of every vote account in the current epoch, for process_data.sh to include in its output.
+# getVoteAccounts only reports the current epoch, so run this during every epoch that will be analyzed.  Requires jq.
+
+# Argument is optional JSON-RPC URL (default https://api.mainnet-beta.solana.com)
+
+# Writes data_stakes/EPOCH, whose lines are of the form:
+# VOTE_ID ACTIVATED_STAKE
+# and data_commissions/EPOCH, whose lines are of the form:
+# VOTE_ID COMMISSION
+
+RPC_URL=${1:-https://api.mainnet-beta.solana.com}
+
+mkdir -p data_stakes
+
+mkdir -p data_commissions
+
+EPOCH=$(curl -s -X POST -H 'Content-Type: application/json' -d '{"jsonrpc":"2.0","id":1,"method":"getEpochInfo"}' \
+            $RPC_URL | jq -e '.result.epoch') || exit -1
+
+VOTE_ACCOUNTS=$(curl -s -X POST -H 'Content-Type: application/json' \
+                    -d '{"jsonrpc":"2.0","id":1,"method":"getVoteAccounts"}' $RPC_URL) || exit -1
+
+echo "$VOTE_ACCOUNTS" | jq -e -r '.result.current[], .result.delinquent[] | "\(.votePubkey) \(.activatedStake)"' \
+    > data_stakes/$EPOCH.tmp && mv data_stakes/$EPOCH.tmp data_stakes/$EPOCH || exit -1
+
+echo "$VOTE_ACCOUNTS" | jq -e -r '.result.current[], .result.delinquent[] | "\(.votePubkey) \(.commission)"' \
+    > data_commissions/$EPOCH.tmp && mv data_commissions/$EPOCH.tmp data_commissions/$EPOCH || exit -1
@@ -2,16 +2,13 @@
 
 # Run this after the data has been pulled from Google Bigtables by the 'fetch_data.sh' script
 
-mkdir -p data_processed
+# Argument is optional minimum coverage: the fraction of blocks that must have been fetched for an epoch to be
+# processed (default 0.99), and optional latency origin: "landing", "parent", or "block" (default "landing"), see
+# README.txt, and optional credit mode: "landed" or "tower" (default "landed"), see README.txt, and optional block
+# slots: if given, each epoch is also split into blocks of that many slots, written into data_blocks/EPOCH for
+# bootstrap_results.sh
 
-ALL_EPOCHS=$(cd data; for i in $(ls); do echo $i | cut -d '.' -f 1; done)
+# Epochs are processed in parallel, one per CPU, and epochs that were already processed from the same data with the
+# same arguments are skipped; set FORCE=1 to process them again (see "pipeline process" in README.txt)
 
-C=0
-for epoch in $ALL_EPOCHS; do
-    (gunzip -c data/$epoch.gz | ./target/release/process_data validators_app_mainnet_beta.json > data_processed/$epoch) &
-    pids[${C}]=$!
-done
-
-for pid in ${pids[*]}; do
-    wait $pid
-done
+./target/release/pipeline ${FORCE:+--force} process "$@"
@@ -0,0 +1,17 @@
+#!/bin/sh
+
+# Run this after the data for the scenario's clusters has been fetched (see fetch_data.sh and fetch_stakes.sh)
+
+# Argument is the path of a scenario file (see README.txt), e.g.:
+# run_scenario.sh scenario_example.toml
+
+# Writes everything into "scenarios/<scenario name>"
+
+SCENARIO=$1
+
+if [ -z "$SCENARIO" ]; then
+    echo "Usage: run_scenario.sh <scenario_file>"
+    exit -1
+fi
+
+./target/release/process_data scenario $SCENARIO && ./target/release/calculate_results scenario $SCENARIO
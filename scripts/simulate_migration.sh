@@ -0,0 +1,43 @@
+#!/bin/sh
+
+# Run this after process_data has written the epoch data into data_processed, for epochs which have stake files (see
+# fetch_stakes.sh)
+
+# Arguments are EPOCHS MOBILITY STICKINESS followed by one or more credit policies (see README.txt), e.g.:
+# simulate_migration.sh 100 0.05 0.01 agave linear:4:60:1 exponential:2:16:0.8
+
+# Writes projections into "migration"
+
+EPOCHS=$1
+MOBILITY=$2
+STICKINESS=$3
+
+if [ -z "$EPOCHS" -o -z "$MOBILITY" -o -z "$STICKINESS" -o -z "$4" ]; then
+    echo "Usage: simulate_migration.sh <epochs> <mobility> <stickiness> <credit_policy> [<credit_policy>...]"
+    exit -1
+fi
+
+shift 3
+
+STAKED_EPOCHS=$(for i in $(cd data_processed; ls); do if [ -f data_stakes/$i ]; then echo -n "$i "; fi; done)
+
+for policy in "$@"; do
+
+    DIR="$(echo -n "$policy" | tr ':,=/' '____')_${EPOCHS}_${MOBILITY}_${STICKINESS}"
+
+    mkdir -p migration/$DIR
+
+    for epoch in $STAKED_EPOCHS; do
+
+        if [ -f data_commissions/$epoch ]; then
+            COMMISSIONS=data_commissions/$epoch
+        else
+            COMMISSIONS=_
+        fi
+
+        ./target/release/calculate_results v $policy migrate $EPOCHS $MOBILITY $STICKINESS $COMMISSIONS \
+            < data_processed/$epoch > migration/$DIR/$epoch
+
+    done
+
+done
@@ -0,0 +1,21 @@
+#!/bin/sh
+
+# Run this after process_data has written the epoch data into data_processed
+
+# Arguments are GRACES MAX_CREDITS MULTIPLIERS, each a comma separated list of values and ranges of the form
+# FIRST..LAST or FIRST..LAST:STEP, e.g.:
+# sweep_results.sh 1..4 16,32..64:8 0.5..2:0.25
+
+# Writes one sweep table over all epochs per level into "sweeps", skipping tables that are up to date; set FORCE=1 to
+# sweep them again (see "pipeline sweep" in README.txt)
+
+GRACES=$1
+MAX_CREDITS=$2
+MULTIPLIERS=$3
+
+if [ -z "$GRACES" -o -z "$MAX_CREDITS" -o -z "$MULTIPLIERS" ]; then
+    echo "Usage: sweep_results.sh <graces> <max_credits> <multipliers>"
+    exit -1
+fi
+
+./target/release/pipeline ${FORCE:+--force} sweep "$GRACES" "$MAX_CREDITS" "$MULTIPLIERS"
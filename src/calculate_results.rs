@@ -1,5 +1,17 @@
+mod concentration;
+mod migration;
+mod rewards;
+mod statistics;
+
+use fetch_data::credit_policy;
+use fetch_data::layout;
+use fetch_data::records::{self, Histogram, ResultRow};
+use fetch_data::scenario;
 use std::collections::HashMap;
 
+// The totals of a validator, computed from its histogram (see records.rs) under a credit policy, or the averages of
+// those of the validators of a data center; each is written out as a ResultRow
+#[derive(Clone)]
 struct Entry
 {
     pub name : String,
@@ -12,187 +24,1174 @@ struct Entry
 
     pub total_latency : u64,
 
-    pub total_validators : u64
+    pub total_validators : u64,
+
+    // Activated stake, in lamports
+    pub stake : u64
+}
+
+// What the entries are
+#[derive(Clone, Copy, PartialEq)]
+enum Grouping
+{
+    Validators,
+
+    // Data centers, with values averaged over their validators
+    DataCenters,
+
+    // Data centers, with values averaged over their validators weighted by stake
+    StakeWeightedDataCenters
+}
+
+// Summary of the outcome of one parameter set of a sweep
+struct SweepOutcome
+{
+    // Highest minus lowest timely credits pct
+    pub spread : f64,
+
+    // Mean absolute difference between an entry's rank by normal credits and its rank by timely credits
+    pub rank_churn : f64,
+
+    // Mean change from normal pct to timely pct, relative to normal pct, of the top and bottom tenths of entries
+    // ranked by normal credits
+    pub top_delta : f64,
+
+    pub bottom_delta : f64
 }
 
 fn main()
 {
     let mut args = std::env::args();
-    args.nth(0);
+    args.next();
 
-    let by_validators = match args
-        .nth(0)
+    // Instead of the arguments below, may be "scenario" and the path of a scenario file (see scenario.rs), to compute
+    // results for every processed epoch of the scenario
+    if std::env::args().nth(1).as_deref() == Some("scenario") {
+        let scenario_path = std::env::args().nth(2).unwrap_or_else(|| {
+            eprintln!("Scenario requires scenario path");
+            std::process::exit(-1);
+        });
+        calculate_scenario(&scenario_path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+        return;
+    }
+
+    let grouping = match args
+        .next()
         .unwrap_or_else(|| {
-            eprintln!("First argument must be \"v\" or \"d\" (for validators or data centers)");
+            eprintln!(
+                "First argument must be \"v\", \"d\", or \"s\" (for validators, data centers, or stake weighted data \
+                 centers)"
+            );
             std::process::exit(-1);
         })
         .as_str()
     {
-        "v" => true,
-        "d" => false,
+        "v" => Grouping::Validators,
+        "d" => Grouping::DataCenters,
+        "s" => Grouping::StakeWeightedDataCenters,
         _ => {
-            eprintln!("First argument must be \"v\" or \"d\" (for validators or data centers)");
+            eprintln!(
+                "First argument must be \"v\", \"d\", or \"s\" (for validators, data centers, or stake weighted data \
+                 centers)"
+            );
             std::process::exit(-1);
         }
     };
-    let grace = args
-        .nth(0)
-        .unwrap_or_else(|| {
-            eprintln!("Second argument must be grace period");
-            std::process::exit(-1);
-        })
-        .parse::<u64>()
-        .unwrap_or_else(|e| {
-            eprintln!("Second argument must be grace period: {}", e);
-            std::process::exit(-1);
-        });
-    let max_credits = args
-        .nth(0)
-        .unwrap_or_else(|| {
+    // Second argument is the credit policy (see credit_policy.rs), or for the linear policy, may be the three
+    // arguments GRACE MAX_CREDITS MULTIPLIER, or may be "sweep" followed by the three arguments GRACES MAX_CREDITS
+    // MULTIPLIERS, each a list of values and ranges (see parse_sweep_values), and optionally one or more paths, each
+    // holding one epoch of process_data output (as for bootstrap), to sweep all of those epochs together rather than
+    // the one epoch on stdin
+    let policy_arg = args.next().unwrap_or_else(|| {
+        eprintln!("Second argument must be credit policy, grace period, or \"sweep\"");
+        std::process::exit(-1);
+    });
+
+    if policy_arg == "sweep" {
+        let mut next_values = |what : &str| {
+            let arg = args.next().unwrap_or_else(|| {
+                eprintln!("Sweep requires {}", what);
+                std::process::exit(-1);
+            });
+            parse_sweep_values(&arg).unwrap_or_else(|e| {
+                eprintln!("Invalid sweep {} {}: {}", what, arg, e);
+                std::process::exit(-1);
+            })
+        };
+        let graces = next_values("graces");
+        let max_credits = next_values("max credits");
+        let multipliers = next_values("multipliers");
+
+        let paths : Vec<String> = args.collect();
+
+        let histograms = if paths.is_empty() {
+            read_histograms(std::io::stdin().lock(), "input")
+        }
+        else {
+            combine_histograms(paths.iter().flat_map(|path| {
+                load_epoch_blocks(path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                })
+            }))
+        };
+
+        sweep(grouping, &histograms, &graces, &max_credits, &multipliers);
+
+        return;
+    }
+
+    let policy_spec = if policy_arg.parse::<u64>().is_ok() {
+        let max_credits = args.next().unwrap_or_else(|| {
             eprintln!("Third argument must be max credits");
             std::process::exit(-1);
-        })
-        .parse::<u64>()
-        .unwrap_or_else(|e| {
-            eprintln!("Third argument must be max credits: {}", e);
-            std::process::exit(-1);
         });
-    let multiplier = args
-        .nth(0)
-        .unwrap_or_else(|| {
+        let multiplier = args.next().unwrap_or_else(|| {
             eprintln!("Fourth argument must be multiplier");
             std::process::exit(-1);
-        })
-        .parse::<f64>()
-        .unwrap_or_else(|e| {
-            eprintln!("Fourth argument must be multiplier: {}", e);
-            std::process::exit(-1);
         });
+        format!("linear:{}:{}:{}", policy_arg, max_credits, multiplier)
+    }
+    else {
+        policy_arg
+    };
+    let policy = credit_policy::parse(&policy_spec).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    });
+
+    // Optionally followed by:
+    // "rewards" and the two arguments REWARD_POOL COMMISSIONS_PATH (see rewards.rs), to compute the rewards that result
+    // from normal credits and from timely credits instead
+    // "theoretical" and the argument SLOTS_PATH, the slot metadata written by fetch_data, to give pcts relative to the
+    // maximum credits achievable in the epoch instead of relative to the best entry
+    // "concentration", to summarize how concentrated rewards are instead (see print_concentration)
+    // "cluster", to write the stake weighted metrics of the whole cluster instead (see print_cluster_metrics)
+    // "migrate" and the four arguments EPOCHS MOBILITY STICKINESS COMMISSIONS_PATH (or _ for no commissions), to
+    // project how stake moves between validators over EPOCHS epochs instead (see migration.rs and simulate_migration)
+    // "bootstrap" and the arguments SAMPLES SEED followed by one or more paths, each holding one epoch of
+    // process_data output, to estimate how certain the differences between normal and timely credits are instead (see
+    // bootstrap); this reads the paths rather than stdin, and must be the last option
+    let mut rewards_args = None;
+
+    let mut concentration = false;
 
-    let stdin = std::io::stdin();
+    let mut cluster = false;
 
-    let mut data_centers = HashMap::<String, Entry>::new();
+    let mut migration_args = None;
 
-    let mut validators = Vec::<Entry>::new();
+    let mut produced_slots = None;
 
-    loop {
-        let mut line = String::new();
+    let mut bootstrap_args = None;
 
-        if stdin.read_line(&mut line).is_err() {
-            break;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "rewards" => {
+                let pool = args
+                    .next()
+                    .unwrap_or_else(|| {
+                        eprintln!("Rewards require reward pool");
+                        std::process::exit(-1);
+                    })
+                    .parse::<rewards::RewardPool>()
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        std::process::exit(-1);
+                    });
+                let commissions_path = args.next().unwrap_or_else(|| {
+                    eprintln!("Rewards require commissions path");
+                    std::process::exit(-1);
+                });
+                let commissions = rewards::load_commissions(&commissions_path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                });
+                rewards_args = Some((pool, commissions));
+            },
+            "concentration" => concentration = true,
+            "cluster" => cluster = true,
+            "migrate" => {
+                let mut next_arg = |what : &str| {
+                    args.next().unwrap_or_else(|| {
+                        eprintln!("Migration requires {}", what);
+                        std::process::exit(-1);
+                    })
+                };
+                let epochs = next_arg("epochs").parse::<u64>().unwrap_or_else(|e| {
+                    eprintln!("Invalid migration epochs: {}", e);
+                    std::process::exit(-1);
+                });
+                let mobility = next_arg("mobility").parse::<f64>().unwrap_or_else(|e| {
+                    eprintln!("Invalid migration mobility: {}", e);
+                    std::process::exit(-1);
+                });
+                let stickiness = next_arg("stickiness").parse::<f64>().unwrap_or_else(|e| {
+                    eprintln!("Invalid migration stickiness: {}", e);
+                    std::process::exit(-1);
+                });
+                let model = migration::MigrationModel::new(mobility, stickiness).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                });
+                let commissions_path = next_arg("commissions path");
+                let commissions = if commissions_path == "_" {
+                    HashMap::new()
+                }
+                else {
+                    rewards::load_commissions(&commissions_path).unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        std::process::exit(-1);
+                    })
+                };
+                migration_args = Some((epochs, model, commissions));
+            },
+            "theoretical" => {
+                let slots_path = args.next().unwrap_or_else(|| {
+                    eprintln!("Theoretical maximum requires slot metadata path");
+                    std::process::exit(-1);
+                });
+                produced_slots = Some(count_produced_slots(&slots_path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                }));
+            },
+            "bootstrap" => {
+                let mut next_arg = |what : &str| {
+                    args.next().unwrap_or_else(|| {
+                        eprintln!("Bootstrap requires {}", what);
+                        std::process::exit(-1);
+                    })
+                };
+                let samples = next_arg("samples").parse::<u64>().unwrap_or_else(|e| {
+                    eprintln!("Invalid bootstrap samples: {}", e);
+                    std::process::exit(-1);
+                });
+                let seed = next_arg("seed").parse::<u64>().unwrap_or_else(|e| {
+                    eprintln!("Invalid bootstrap seed: {}", e);
+                    std::process::exit(-1);
+                });
+                let paths : Vec<String> = args.by_ref().collect();
+                if samples == 0 || paths.is_empty() {
+                    eprintln!("Bootstrap requires at least one sample and at least one epoch path");
+                    std::process::exit(-1);
+                }
+                bootstrap_args = Some((samples, seed, paths));
+            },
+            _ => {
+                eprintln!("Unexpected argument {}", arg);
+                std::process::exit(-1);
+            }
         }
+    }
+
+    if let Some((samples, seed, paths)) = bootstrap_args {
+        let epochs : Vec<Vec<Vec<Histogram>>> = paths
+            .iter()
+            .map(|path| {
+                load_epoch_blocks(path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                })
+            })
+            .collect();
+        bootstrap(grouping, &epochs, policy.as_ref(), samples, seed);
+        return;
+    }
+
+    let histograms = read_histograms(std::io::stdin().lock(), "input");
 
-        line.truncate(line.len() - 1);
+    // Stakes are only known if every line of input has them
+    let with_stakes = !histograms.is_empty() && histograms.iter().all(|h| h.stake.is_some());
 
-        if line.len() == 0 {
-            break;
+    if concentration {
+        print_concentration(&histograms, policy.as_ref());
+        return;
+    }
+
+    if cluster {
+        if !with_stakes {
+            eprintln!("Cluster metrics require input with stakes (see process_data)");
+            std::process::exit(-1);
         }
+        print_cluster_metrics(&entries(Grouping::Validators, &histograms, policy.as_ref()));
+        return;
+    }
 
-        let mut split : Vec<&str> = line.split(" ").collect();
+    if let Some((epochs, model, commissions)) = migration_args {
+        if !with_stakes {
+            eprintln!("Migration requires input with stakes (see process_data)");
+            std::process::exit(-1);
+        }
+        simulate_migration(&histograms, policy.as_ref(), epochs, &model, &commissions);
+        return;
+    }
 
-        // data_center
-        // vote_account
-        // total_transactions
-        // total_credits
-        // 64 slots
-        if split.len() != 68 {
-            eprintln!("Invalid input line: {}", line);
+    if let Some((pool, commissions)) = rewards_args {
+        if !with_stakes {
+            eprintln!("Rewards require input with stakes (see process_data)");
             std::process::exit(-1);
         }
+        print_rewards(grouping, &histograms, policy.as_ref(), &pool, &commissions);
+        return;
+    }
 
-        let data_center = split.remove(0);
+    if grouping == Grouping::StakeWeightedDataCenters && !with_stakes {
+        eprintln!("Stake weighted data centers require input with stakes (see process_data)");
+        std::process::exit(-1);
+    }
 
-        let vote_account = split.remove(0);
+    // Pcts are relative to the best entry or to the most credits achievable in the epoch, neither of which is known
+    // without input
+    if histograms.is_empty() {
+        eprintln!("No input");
+        std::process::exit(-1);
+    }
 
-        let total_transactions = split.remove(0).to_string().parse::<u64>().unwrap_or_else(|e| {
-            eprintln!("Invalid input line (total transactions {}): {}", e, line);
-            std::process::exit(-1);
-        });
+    let entries = entries(grouping, &histograms, policy.as_ref());
 
-        let total_credits = split.remove(0).to_string().parse::<u64>().unwrap_or_else(|e| {
-            eprintln!("Invalid input line (total credits {}): {}", e, line);
-            std::process::exit(-1);
+    // Every slot that produced a block can be voted on once, for a credit, which under the credit policy is worth at
+    // most the most credits that the policy gives for any latency that a histogram counts
+    let (max_total_credits, max_total_timely_credits) = match produced_slots {
+        Some(produced_slots) => {
+            let max_policy_credits =
+                (0..(records::LATENCIES as u64)).map(|latency| policy.credits(latency)).max().unwrap_or(0);
+            (produced_slots as f64, (produced_slots * max_policy_credits) as f64)
+        },
+        None => (
+            entries.iter().map(|e| e.total_credits).max().unwrap_or(0) as f64,
+            entries.iter().map(|e| e.total_timely_credits).max().unwrap_or(0) as f64
+        )
+    };
+
+    let total_stake = entries.iter().map(|e| e.stake).sum::<u64>() as f64;
+
+    for entry in entries {
+        println!("{}", ResultRow {
+            name : entry.name,
+            validators : entry.total_validators,
+            transactions : entry.total_transactions,
+            credits : entry.total_credits,
+            timely_credits : entry.total_timely_credits,
+            avg_latency : (entry.total_latency as f64) / (entry.total_credits as f64),
+            normal_pct : (entry.total_credits as f64) / max_total_credits,
+            timely_pct : (entry.total_timely_credits as f64) / max_total_timely_credits,
+            // Stake and share of total stake, if known
+            stake : with_stakes.then(|| (entry.stake, (entry.stake as f64) / total_stake))
         });
+    }
+}
 
-        let mut total_latency = 0_u64;
+// Computes results for every epoch that process_data has processed for a scenario, just as calculate_results.sh does
+// for the top level directories, for each of the scenario's credit policies and aggregation levels.  Results are written
+// under each cluster's scenario directory into results/POLICY, named as by calculate_results.sh (v_EPOCH, d_EPOCH,
+// s_EPOCH, c_EPOCH, and m_EPOCH), with .csv appended for the csv format.
+fn calculate_scenario(scenario_path : &String) -> Result<(), String>
+{
+    let scenario = scenario::load(scenario_path)?;
 
-        let mut total_timely_credits = 0_u64;
+    for cluster in &scenario.clusters {
+        let directory = scenario.directory(cluster);
 
-        for i in 0..split.len() {
-            let slots_at_this_latency = split[i].parse::<u64>().unwrap_or_else(|e| {
-                eprintln!("Invalid input line ({}): {}", e, line);
-                std::process::exit(-1);
-            });
+        let processed_directory = directory.join("data_processed");
+
+        let epochs : Vec<u64> = layout::list_epochs(&processed_directory, "")?
+            .into_iter()
+            .filter(|epoch| cluster.includes(*epoch))
+            .collect();
+
+        for policy in &scenario.policies {
+            let spec = policy.spec()?;
+
+            let results_directory = directory.join("results").join(&policy.name);
+
+            std::fs::create_dir_all(&results_directory)
+                .map_err(|e| format!("Error creating {}: {}", results_directory.display(), e))?;
 
-            let i = i as u64;
+            for epoch in &epochs {
+                let processed_path = processed_directory.join(epoch.to_string());
 
-            total_latency += i * slots_at_this_latency;
+                // process_data includes stakes in its output exactly when it was given them
+                let with_stakes = scenario.metadata_path(cluster, "data_stakes", *epoch).is_some();
 
-            // values of i from 0 to (grace - 1) (inclusive) should get full credits
+                let mut normalize = vec![];
 
-            let slot_credits_reduction = (((std::cmp::max(i, grace - 1) - (grace - 1)) as f64) * multiplier) as u64;
+                if scenario.normalize == "theoretical" {
+                    match scenario.metadata_path(cluster, "data_slots", *epoch) {
+                        Some(slots_path) => normalize = vec!["theoretical".to_string(), slots_path],
+                        None => {
+                            eprintln!("Skipping cluster {} epoch {}: no slot metadata", cluster.name, epoch);
+                            continue;
+                        }
+                    }
+                }
 
-            let slot_credits =
-                if max_credits > slot_credits_reduction { max_credits - slot_credits_reduction } else { 1 };
+                let run = |args : Vec<String>| {
+                    let processed = std::fs::File::open(&processed_path)
+                        .map_err(|e| format!("Error opening {}: {}", processed_path.display(), e))?;
+                    scenario::run_self(&args, Some(Box::new(processed)))
+                };
 
-            total_timely_credits += slot_credits * slots_at_this_latency;
+                let write = |prefix : &str, output : &String, header : Option<&str>| {
+                    for format in &scenario.formats {
+                        let (path, contents) = match format.as_str() {
+                            "csv" => {
+                                (results_directory.join(format!("{}_{}.csv", prefix, epoch)), to_csv(output, header))
+                            },
+                            _ => (results_directory.join(format!("{}_{}", prefix, epoch)), output.clone())
+                        };
+                        std::fs::write(&path, contents)
+                            .map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+                    }
+                    Ok::<(), String>(())
+                };
+
+                for level in &scenario.levels {
+                    let prefix = match level.as_str() {
+                        "validators" => "v",
+                        "data_centers" => "d",
+                        _ => "s"
+                    };
+                    // Only epochs processed with stakes have stake weighted data centers
+                    if prefix == "s" && !with_stakes {
+                        continue;
+                    }
+                    let mut args = vec![prefix.to_string(), spec.clone()];
+                    args.extend(normalize.iter().cloned());
+                    let header = if with_stakes {
+                        "NAME VALIDATORS TRANSACTIONS CREDITS TIMELY_CREDITS AVG_LATENCY NORMAL_PCT TIMELY_PCT STAKE \
+                         STAKE_SHARE"
+                    }
+                    else {
+                        "NAME VALIDATORS TRANSACTIONS CREDITS TIMELY_CREDITS AVG_LATENCY NORMAL_PCT TIMELY_PCT"
+                    };
+                    write(prefix, &run(args)?, Some(header))?;
+                }
+
+                write("c", &run(vec!["v".to_string(), spec.clone(), "concentration".to_string()])?, None)?;
+
+                if with_stakes {
+                    write("m", &run(vec!["v".to_string(), spec.clone(), "cluster".to_string()])?, None)?;
+                }
+            }
         }
+    }
 
-        validators.push(Entry {
-            name : vote_account.to_string(),
-            total_transactions,
-            total_credits,
-            total_timely_credits,
-            total_latency,
-            total_validators : 1
-        });
+    Ok(())
+}
+
+// Converts space separated output to CSV, with the given header (if the output doesn't have its own "# " header lines)
+fn to_csv(
+    output : &str,
+    header : Option<&str>
+) -> String
+{
+    header
+        .into_iter()
+        .chain(output.lines())
+        .map(|line| format!("{}\n", line.trim_start_matches('#').split_whitespace().collect::<Vec<&str>>().join(",")))
+        .collect()
+}
+
+// Writes the rewards of each validator or data center under normal credits and under timely credits, in lines of the
+// form:
+// NAME STAKE NORMAL_VALIDATOR_REWARDS NORMAL_STAKER_REWARDS TIMELY_VALIDATOR_REWARDS TIMELY_STAKER_REWARDS
+//     REWARDS_CHANGE NORMAL_APY TIMELY_APY APY_CHANGE
+// in order of name.  The rewards of a data center are the sums of those of its validators, so stake weighted and plain
+// data centers are the same.  Vote accounts without a commission are taken to have 0% commission.
+fn print_rewards(
+    grouping : Grouping,
+    histograms : &[Histogram],
+    policy : &dyn credit_policy::CreditPolicy,
+    pool : &rewards::RewardPool,
+    commissions : &HashMap<String, u8>
+)
+{
+    // In the same order as histograms
+    let validators = entries(Grouping::Validators, histograms, policy);
+
+    let commission = |e : &Entry| commissions.get(&e.name).cloned().unwrap_or(0);
+
+    let normal_rewards = rewards::split_rewards(
+        pool,
+        &validators.iter().map(|e| (e.stake, e.total_credits, commission(e))).collect::<Vec<(u64, u64, u8)>>()
+    );
+
+    let timely_rewards = rewards::split_rewards(
+        pool,
+        &validators.iter().map(|e| (e.stake, e.total_timely_credits, commission(e))).collect::<Vec<(u64, u64, u8)>>()
+    );
+
+    // Map from name to (stake, normal rewards, timely rewards)
+    let mut totals = HashMap::<&String, (u64, rewards::Rewards, rewards::Rewards)>::new();
+
+    for (i, validator) in validators.iter().enumerate() {
+        let name = if grouping == Grouping::Validators { &validator.name } else { &histograms[i].data_center };
+        let total = totals.entry(name).or_default();
+        total.0 += validator.stake;
+        total.1 += normal_rewards[i];
+        total.2 += timely_rewards[i];
+    }
+
+    // In order of name, so that the output is the same from run to run
+    let mut totals : Vec<(&String, (u64, rewards::Rewards, rewards::Rewards))> = totals.into_iter().collect();
+
+    totals.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, (stake, normal, timely)) in totals {
+        let normal_apy = normal.staker_apy(stake, pool.epochs_per_year);
+        let timely_apy = timely.staker_apy(stake, pool.epochs_per_year);
+        println!(
+            "{} {} {} {} {} {} {} {} {} {}",
+            name,
+            stake,
+            normal.validator,
+            normal.stakers,
+            timely.validator,
+            timely.stakers,
+            (timely.total() as i128) - (normal.total() as i128),
+            normal_apy,
+            timely_apy,
+            timely_apy - normal_apy
+        );
+    }
+}
+
+// Writes a summary of how concentrated the share of rewards is among validators, data centers, ASNs, and countries,
+// under normal credits and under timely credits, in lines of the form:
+// LEVEL REGIME ENTITIES NAKAMOTO_COEFFICIENT GINI_COEFFICIENT HHI
+// A validator's share of rewards is proportional to stake * credits, or to credits alone if the input has no stakes.
+// The ASN and country of a validator are taken from its data center, whose name is of the form ASN-COUNTRY-CITY;
+// validators of unknown data center are grouped together as "_".
+fn print_concentration(
+    histograms : &[Histogram],
+    policy : &dyn credit_policy::CreditPolicy
+)
+{
+    // In the same order as histograms
+    let validators = entries(Grouping::Validators, histograms, policy);
+
+    // The entity that a validator belongs to at each level
+    type EntityName = fn(&Histogram) -> String;
+
+    // The credits of a validator under each regime
+    type Credits = fn(&Entry) -> u64;
+
+    let level_names : [(&str, EntityName); 4] = [
+        ("validator", |h| h.vote_account.clone()),
+        ("data_center", |h| h.data_center.clone()),
+        ("asn", |h| h.data_center.split('-').next().unwrap_or("_").to_string()),
+        ("country", |h| h.data_center.split('-').nth(1).unwrap_or("_").to_string())
+    ];
+
+    let regimes : [(&str, Credits); 2] = [("normal", |e| e.total_credits), ("timely", |e| e.total_timely_credits)];
 
-        let mut to_insert = match data_centers.remove(data_center) {
-            Some(to_insert) => to_insert,
-            None => Entry {
-                name : data_center.to_string(),
-                total_transactions : 0_u64,
-                total_credits : 0_u64,
-                total_timely_credits : 0_u64,
-                total_latency : 0_u64,
-                total_validators : 0_u64
+    println!("# LEVEL REGIME ENTITIES NAKAMOTO_COEFFICIENT GINI_COEFFICIENT HHI");
+
+    for (level, name) in level_names {
+        for (regime, credits) in regimes {
+            let mut points = HashMap::<String, f64>::new();
+            for (histogram, validator) in histograms.iter().zip(validators.iter()) {
+                *points.entry(name(histogram)).or_insert(0_f64) +=
+                    (histogram.stake.unwrap_or(1) as f64) * (credits(validator) as f64);
             }
-        };
+            let points : Vec<f64> = points.into_values().collect();
+            println!(
+                "{} {} {} {} {} {}",
+                level,
+                regime,
+                points.len(),
+                concentration::nakamoto_coefficient(&points),
+                concentration::gini_coefficient(&points),
+                concentration::herfindahl_hirschman_index(&points)
+            );
+        }
+    }
+}
+
+// Projects how stake moves between validators over the given number of epochs, if every epoch they earn the same
+// credits as in the input epoch, and stakers seek the highest yield, i.e. credits less commission.  This is done both
+// for normal credits and for timely credits.  Writes how concentrated stake is after each epoch, in lines of the form:
+// REGIME EPOCH VALIDATOR_NAKAMOTO VALIDATOR_GINI VALIDATOR_HHI DATA_CENTER_NAKAMOTO DATA_CENTER_GINI DATA_CENTER_HHI
+// where EPOCH is the number of epochs since the input epoch, followed by the projected stake of each validator at the
+// end, in lines of the form:
+// REGIME VOTE_ID DATA_CENTER INITIAL_STAKE PROJECTED_STAKE
+// Vote accounts without a commission are taken to have 0% commission.
+fn simulate_migration(
+    histograms : &[Histogram],
+    policy : &dyn credit_policy::CreditPolicy,
+    epochs : u64,
+    model : &migration::MigrationModel,
+    commissions : &HashMap<String, u8>
+)
+{
+    // In the same order as histograms
+    let validators = entries(Grouping::Validators, histograms, policy);
+
+    // The credits of a validator under each regime
+    type Credits = fn(&Entry) -> u64;
+
+    let regimes : [(&str, Credits); 2] = [("normal", |e| e.total_credits), ("timely", |e| e.total_timely_credits)];
+
+    let data_center_stakes = |stakes : &[f64]| {
+        let mut data_center_stakes = HashMap::<&String, f64>::new();
+        for (histogram, stake) in histograms.iter().zip(stakes) {
+            *data_center_stakes.entry(&histogram.data_center).or_insert(0_f64) += stake;
+        }
+        data_center_stakes.into_values().collect::<Vec<f64>>()
+    };
+
+    let mut projections = vec![];
 
-        to_insert.total_transactions += total_transactions;
-        to_insert.total_credits += total_credits;
-        to_insert.total_timely_credits += total_timely_credits;
-        to_insert.total_latency += total_latency;
-        to_insert.total_validators += 1;
+    println!(
+        "# REGIME EPOCH VALIDATOR_NAKAMOTO VALIDATOR_GINI VALIDATOR_HHI DATA_CENTER_NAKAMOTO DATA_CENTER_GINI \
+         DATA_CENTER_HHI"
+    );
 
-        data_centers.insert(data_center.to_string(), to_insert);
+    for (regime, credits) in regimes {
+        let yields : Vec<f64> = validators
+            .iter()
+            .map(|e| {
+                (credits(e) as f64) * (1_f64 - ((commissions.get(&e.name).cloned().unwrap_or(0) as f64) / 100_f64))
+            })
+            .collect();
+
+        let mut stakes : Vec<f64> = validators.iter().map(|e| e.stake as f64).collect();
+
+        for epoch in 0..=epochs {
+            if epoch > 0 {
+                model.step(&mut stakes, &yields);
+            }
+            let data_center_stakes = data_center_stakes(&stakes);
+            println!(
+                "{} {} {} {} {} {} {} {}",
+                regime,
+                epoch,
+                concentration::nakamoto_coefficient(&stakes),
+                concentration::gini_coefficient(&stakes),
+                concentration::herfindahl_hirschman_index(&stakes),
+                concentration::nakamoto_coefficient(&data_center_stakes),
+                concentration::gini_coefficient(&data_center_stakes),
+                concentration::herfindahl_hirschman_index(&data_center_stakes)
+            );
+        }
+
+        projections.push((regime, stakes));
     }
 
-    // Now average out the values for the data centers
-    for (_, entry) in &mut data_centers {
-        entry.total_transactions /= entry.total_validators;
-        entry.total_credits /= entry.total_validators;
-        entry.total_timely_credits /= entry.total_validators;
-        entry.total_latency /= entry.total_validators;
+    println!("# REGIME VOTE_ID DATA_CENTER INITIAL_STAKE PROJECTED_STAKE");
+
+    for (regime, stakes) in projections {
+        for ((histogram, validator), stake) in histograms.iter().zip(validators.iter()).zip(stakes) {
+            println!(
+                "{} {} {} {} {}",
+                regime,
+                validator.name,
+                histogram.data_center,
+                validator.stake,
+                stake.round() as u64
+            );
+        }
     }
+}
 
-    let entries = if by_validators { validators } else { data_centers.into_iter().map(|(_, entry)| entry).collect() };
+// Estimates, by bootstrap resampling, how certain the differences between normal and timely credits are.  Each sample
+// draws as many epochs as were given, with replacement, and for each drawn epoch that was split into blocks of slots,
+// draws as many of its blocks as it has, with replacement; the votes of everything drawn are summed.  Writes the rank
+// correlation between normal and timely credits, in lines of the form:
+// STATISTIC VALUE LOW HIGH
+// for the kendall_tau and spearman statistics, followed by the change of each validator or data center, in lines of
+// the form:
+// NAME DELTA DELTA_LOW DELTA_HIGH RANK_CHANGE RANK_CHANGE_LOW RANK_CHANGE_HIGH
+// where DELTA is the change from normal pct to timely pct relative to normal pct, and RANK_CHANGE is how many places
+// higher the entry ranks by timely credits than by normal credits.  Values are from all of the data, and LOW to HIGH is
+// their 95% confidence interval over the samples (from only those samples that include the entry).  A validator's
+// stake is its stake in the last epoch or block that has it.
+fn bootstrap(
+    grouping : Grouping,
+    epochs : &[Vec<Vec<Histogram>>],
+    policy : &dyn credit_policy::CreditPolicy,
+    samples : u64,
+    seed : u64
+)
+{
+    // The validator entries of each block of each epoch, with their data centers, computed once up front so that each
+    // sample only has to sum them
+    let epochs : Vec<Vec<Vec<(&String, Entry)>>> = epochs
+        .iter()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .map(|block| block.iter().map(|h| (&h.data_center, validator_entry(h, policy))).collect())
+                .collect()
+        })
+        .collect();
+
+    let combine = |blocks : &[&Vec<(&String, Entry)>]| {
+        let mut validators = HashMap::<&String, (&String, Entry)>::new();
+        for block in blocks {
+            for (data_center, entry) in block.iter() {
+                match validators.get_mut(&entry.name) {
+                    Some((_, total)) => {
+                        total.total_transactions += entry.total_transactions;
+                        total.total_credits += entry.total_credits;
+                        total.total_timely_credits += entry.total_timely_credits;
+                        total.total_latency += entry.total_latency;
+                        if entry.stake > 0 {
+                            total.stake = entry.stake;
+                        }
+                    },
+                    None => {
+                        validators.insert(&entry.name, (data_center, entry.clone()));
+                    }
+                }
+            }
+        }
+        group_entries(grouping, validators.into_values().collect())
+    };
 
-    let max_total_credits = entries.iter().map(|e| e.total_credits).max().unwrap() as f64;
+    let all_blocks : Vec<&Vec<(&String, Entry)>> = epochs.iter().flatten().collect();
 
-    let max_total_timely_credits = entries.iter().map(|e| e.total_timely_credits).max().unwrap() as f64;
+    let (kendall_tau, spearman_rho, changes) = bootstrap_outcome(&combine(&all_blocks));
 
-    for entry in entries {
+    let mut rng = statistics::Rng::new(seed);
+
+    let mut kendall_taus = vec![];
+
+    let mut spearman_rhos = vec![];
+
+    // Map from name to the (delta, rank change) of each sample that includes it
+    let mut sampled_changes = HashMap::<String, Vec<(f64, f64)>>::new();
+
+    for _ in 0..samples {
+        let mut blocks = vec![];
+        for _ in 0..epochs.len() {
+            let epoch = &epochs[rng.below(epochs.len())];
+            for _ in 0..epoch.len() {
+                blocks.push(&epoch[rng.below(epoch.len())]);
+            }
+        }
+
+        let (kendall_tau, spearman_rho, changes) = bootstrap_outcome(&combine(&blocks));
+
+        kendall_taus.push(kendall_tau);
+        spearman_rhos.push(spearman_rho);
+
+        for (name, change) in changes {
+            sampled_changes.entry(name).or_default().push(change);
+        }
+    }
+
+    let interval = |values : &mut Vec<f64>| {
+        values.retain(|value| value.is_finite());
+        statistics::percentile_interval(values, 2.5, 97.5)
+    };
+
+    println!("# STATISTIC VALUE LOW HIGH");
+
+    let (low, high) = interval(&mut kendall_taus);
+
+    println!("kendall_tau {} {} {}", kendall_tau, low, high);
+
+    let (low, high) = interval(&mut spearman_rhos);
+
+    println!("spearman {} {} {}", spearman_rho, low, high);
+
+    println!("# NAME DELTA DELTA_LOW DELTA_HIGH RANK_CHANGE RANK_CHANGE_LOW RANK_CHANGE_HIGH");
+
+    let mut changes : Vec<(String, (f64, f64))> = changes.into_iter().collect();
+
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, (delta, rank_change)) in changes {
+        let sampled = sampled_changes.remove(&name).unwrap_or_default();
+        let (delta_low, delta_high) = interval(&mut sampled.iter().map(|change| change.0).collect());
+        let (rank_change_low, rank_change_high) = interval(&mut sampled.iter().map(|change| change.1).collect());
         println!(
-            "{} {} {} {} {} {} {} {}",
-            entry.name,
-            entry.total_validators,
-            entry.total_transactions,
-            entry.total_credits,
-            entry.total_timely_credits,
-            (entry.total_latency as f64) / (entry.total_credits as f64),
-            (entry.total_credits as f64) / max_total_credits,
-            (entry.total_timely_credits as f64) / max_total_timely_credits,
+            "{} {} {} {} {} {} {}",
+            name, delta, delta_low, delta_high, rank_change, rank_change_low, rank_change_high
         );
     }
 }
+
+// Returns the Kendall tau and Spearman rank correlations between the normal and timely credits of the entries, and a
+// map from the name of each entry to its (delta, rank change), as described for bootstrap
+fn bootstrap_outcome(entries : &[Entry]) -> (f64, f64, HashMap<String, (f64, f64)>)
+{
+    let normal_credits : Vec<f64> = entries.iter().map(|e| e.total_credits as f64).collect();
+
+    let timely_credits : Vec<f64> = entries.iter().map(|e| e.total_timely_credits as f64).collect();
+
+    let max_total_credits = normal_credits.iter().cloned().fold(0_f64, f64::max);
+
+    let max_total_timely_credits = timely_credits.iter().cloned().fold(0_f64, f64::max);
+
+    // Rank by normal credits and by timely credits, breaking ties by name so that ranks are deterministic
+    let ranks = |credits : fn(&Entry) -> u64| {
+        let mut ranking : Vec<&Entry> = entries.iter().collect();
+        ranking.sort_by(|a, b| credits(b).cmp(&credits(a)).then(a.name.cmp(&b.name)));
+        ranking.into_iter().enumerate().map(|(rank, e)| (&e.name, rank)).collect::<HashMap<&String, usize>>()
+    };
+
+    let normal_ranks = ranks(|e| e.total_credits);
+
+    let timely_ranks = ranks(|e| e.total_timely_credits);
+
+    let changes = entries
+        .iter()
+        .map(|e| {
+            let normal_pct = (e.total_credits as f64) / max_total_credits;
+            let timely_pct = (e.total_timely_credits as f64) / max_total_timely_credits;
+            let rank_change = (normal_ranks[&e.name] as f64) - (timely_ranks[&e.name] as f64);
+            (e.name.clone(), ((timely_pct - normal_pct) / normal_pct, rank_change))
+        })
+        .collect();
+
+    (
+        statistics::kendall_tau(&normal_credits, &timely_credits),
+        statistics::spearman_rho(&normal_credits, &timely_credits),
+        changes
+    )
+}
+
+// Loads one epoch of process_data output, which is either a file, or a directory of files each holding the output for
+// one block of slots of the epoch (see process_data), as a list of blocks
+fn load_epoch_blocks(path : &String) -> Result<Vec<Vec<Histogram>>, String>
+{
+    let open = |path : &std::path::Path| {
+        std::fs::File::open(path)
+            .map(|file| read_histograms(std::io::BufReader::new(file), &path.display().to_string()))
+            .map_err(|e| format!("Error opening {}: {}", path.display(), e))
+    };
+
+    let path = std::path::Path::new(path);
+
+    if !path.is_dir() {
+        return Ok(vec![open(path)?]);
+    }
+
+    let mut block_paths = std::fs::read_dir(path)
+        .and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+
+    block_paths.sort();
+
+    let blocks = block_paths.iter().map(|path| open(path)).collect::<Result<Vec<_>, _>>()?;
+
+    if blocks.is_empty() {
+        return Err(format!("No blocks in {}", path.display()));
+    }
+
+    Ok(blocks)
+}
+
+// Writes the stake weighted metrics of the whole cluster, in a line of the form:
+// VALIDATORS STAKE AVG_LATENCY TIMELY_CREDITS_PER_CREDIT
+// where AVG_LATENCY is the stake weighted mean vote latency, and TIMELY_CREDITS_PER_CREDIT is the stake weighted
+// number of timely credits earned per normal credit
+fn print_cluster_metrics(validators : &[Entry])
+{
+    let total_stake = validators.iter().map(|e| e.stake).sum::<u64>();
+
+    let weighted =
+        |value : fn(&Entry) -> u64| validators.iter().map(|e| (e.stake as f64) * (value(e) as f64)).sum::<f64>();
+
+    let weighted_credits = weighted(|e| e.total_credits);
+
+    println!("# VALIDATORS STAKE AVG_LATENCY TIMELY_CREDITS_PER_CREDIT");
+
+    println!(
+        "{} {} {} {}",
+        validators.len(),
+        total_stake,
+        weighted(|e| e.total_latency) / weighted_credits,
+        weighted(|e| e.total_timely_credits) / weighted_credits
+    );
+}
+
+// Returns the number of slots that produced a block, from slot metadata (which may be compressed with gzip) whose
+// lines, after a "#" header, each give one such slot
+fn count_produced_slots(path : &String) -> Result<u64, String>
+{
+    let mut count = 0_u64;
+
+    for line in std::io::BufRead::lines(records::open(std::path::Path::new(path))?) {
+        let line = line.map_err(|e| format!("Error reading slot metadata {}: {}", path, e))?;
+        if !line.is_empty() && !line.starts_with('#') {
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+// Combines the histograms of several epochs, or blocks of slots, into one per validator, summing its counts.  A
+// validator's data center and stake are those of the last epoch or block that has it.
+fn combine_histograms(blocks : impl Iterator<Item = Vec<Histogram>>) -> Vec<Histogram>
+{
+    let mut validators = HashMap::<String, Histogram>::new();
+
+    for histogram in blocks.flatten() {
+        match validators.get_mut(&histogram.vote_account) {
+            Some(total) => {
+                total.data_center = histogram.data_center;
+                total.total_transactions += histogram.total_transactions;
+                total.total_credits += histogram.total_credits;
+                for (total, count) in total.latencies.iter_mut().zip(histogram.latencies) {
+                    *total += count;
+                }
+                if histogram.stake.is_some() {
+                    total.stake = histogram.stake;
+                }
+            },
+            None => {
+                validators.insert(histogram.vote_account.clone(), histogram);
+            }
+        }
+    }
+
+    let mut histograms : Vec<Histogram> = validators.into_values().collect();
+
+    histograms.sort_by(|a, b| a.vote_account.cmp(&b.vote_account));
+
+    histograms
+}
+
+// Reads process_data output
+fn read_histograms(
+    input : impl std::io::BufRead,
+    source : &str
+) -> Vec<Histogram>
+{
+    records::read(input, source).collect::<Result<Vec<Histogram>, String>>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    })
+}
+
+// Computes the entries, of validators or of data centers, that result from applying a credit policy
+fn entries(
+    grouping : Grouping,
+    histograms : &[Histogram],
+    policy : &dyn credit_policy::CreditPolicy
+) -> Vec<Entry>
+{
+    group_entries(grouping, histograms.iter().map(|h| (&h.data_center, validator_entry(h, policy))).collect())
+}
+
+// Computes the entry of one validator that results from applying a credit policy
+fn validator_entry(
+    histogram : &Histogram,
+    policy : &dyn credit_policy::CreditPolicy
+) -> Entry
+{
+    let mut total_latency = 0_u64;
+
+    let mut total_timely_credits = 0_u64;
+
+    for (i, slots_at_this_latency) in histogram.latencies.iter().enumerate() {
+        let i = i as u64;
+
+        total_latency += i * slots_at_this_latency;
+
+        total_timely_credits += policy.credits(i) * slots_at_this_latency;
+    }
+
+    Entry {
+        name : histogram.vote_account.clone(),
+        total_transactions : histogram.total_transactions,
+        total_credits : histogram.total_credits,
+        total_timely_credits,
+        total_latency,
+        total_validators : 1,
+        stake : histogram.stake.unwrap_or(0)
+    }
+}
+
+// Groups validator entries, each given with its data center, into the entries of the grouping
+fn group_entries(
+    grouping : Grouping,
+    validators : Vec<(&String, Entry)>
+) -> Vec<Entry>
+{
+    if grouping == Grouping::Validators {
+        return validators.into_iter().map(|(_, entry)| entry).collect();
+    }
+
+    let mut data_centers = HashMap::<&String, Vec<Entry>>::new();
+
+    for (data_center, entry) in validators {
+        data_centers.entry(data_center).or_default().push(entry);
+    }
+
+    // Now average out the values for the data centers
+    data_centers
+        .into_iter()
+        .map(|(data_center, members)| {
+            let total_validators = members.len() as u64;
+
+            let stake = members.iter().map(|e| e.stake).sum::<u64>();
+
+            // Stake weighted averages fall back to plain averages for data centers with no stake at all.  Both are
+            // computed in f64 and rounded to the nearest whole value here, so that the two groupings are comparable.
+            let average = |value : fn(&Entry) -> u64| {
+                let mean = if grouping == Grouping::StakeWeightedDataCenters && stake > 0 {
+                    members.iter().map(|e| (e.stake as f64) * (value(e) as f64)).sum::<f64>() / (stake as f64)
+                }
+                else {
+                    members.iter().map(|e| value(e) as f64).sum::<f64>() / (total_validators as f64)
+                };
+                mean.round() as u64
+            };
+
+            Entry {
+                name : data_center.clone(),
+                total_transactions : average(|e| e.total_transactions),
+                total_credits : average(|e| e.total_credits),
+                total_timely_credits : average(|e| e.total_timely_credits),
+                total_latency : average(|e| e.total_latency),
+                total_validators,
+                stake
+            }
+        })
+        .collect()
+}
+
+// Parses a comma separated list of sweep values, each of which is either a single value or an inclusive range of the
+// form FIRST..LAST or FIRST..LAST:STEP (STEP defaulting to 1)
+fn parse_sweep_values(arg : &str) -> Result<Vec<f64>, String>
+{
+    let parse = |value : &str| value.parse::<f64>().map_err(|e| format!("{} ({})", e, value));
+
+    let mut values = vec![];
+
+    for item in arg.split(',') {
+        match item.split_once("..") {
+            Some((first, rest)) => {
+                let (last, step) = match rest.split_once(':') {
+                    Some((last, step)) => (parse(last)?, parse(step)?),
+                    None => (parse(rest)?, 1_f64)
+                };
+                let first = parse(first)?;
+                if step <= 0_f64 {
+                    return Err(format!("step of range {} must be positive", item));
+                }
+                // Count steps rather than accumulating the step, so that rounding doesn't drop the last value
+                let count = (((last - first) / step) + 1e-9).floor();
+                if count < 0_f64 {
+                    return Err(format!("range {} is empty", item));
+                }
+                values.extend((0..=(count as u64)).map(|i| first + ((i as f64) * step)));
+            },
+            None => values.push(parse(item)?)
+        }
+    }
+
+    Ok(values)
+}
+
+// Evaluates the linear credit policy for every combination of the given parameters, and writes one line per
+// parameter set of the form:
+// GRACE MAX_CREDITS MULTIPLIER SPREAD RANK_CHURN TOP_DELTA BOTTOM_DELTA
+fn sweep(
+    grouping : Grouping,
+    histograms : &[Histogram],
+    graces : &[f64],
+    max_credits : &[f64],
+    multipliers : &[f64]
+)
+{
+    if histograms.is_empty() {
+        eprintln!("No input to sweep");
+        std::process::exit(-1);
+    }
+
+    println!("# GRACE MAX_CREDITS MULTIPLIER SPREAD RANK_CHURN TOP_DELTA BOTTOM_DELTA");
+
+    for grace in graces {
+        for max_credits in max_credits {
+            for multiplier in multipliers {
+                // The linear policy's grace and max credits must be integers, which parse will insist on
+                let spec = format!("linear:{}:{}:{}", grace, max_credits, multiplier);
+                let policy = credit_policy::parse(&spec).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                });
+
+                let outcome = sweep_outcome(&entries(grouping, histograms, policy.as_ref())).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(-1);
+                });
+
+                println!(
+                    "{} {} {} {} {} {} {}",
+                    grace,
+                    max_credits,
+                    multiplier,
+                    outcome.spread,
+                    outcome.rank_churn,
+                    outcome.top_delta,
+                    outcome.bottom_delta
+                );
+            }
+        }
+    }
+}
+
+fn sweep_outcome(entries : &[Entry]) -> Result<SweepOutcome, String>
+{
+    let max_total_credits = entries.iter().map(|e| e.total_credits).max().ok_or("No entries to sweep")? as f64;
+
+    let max_total_timely_credits = entries.iter().map(|e| e.total_timely_credits).max().unwrap_or(0) as f64;
+
+    if max_total_timely_credits == 0_f64 {
+        return Err("No credits to sweep".to_string());
+    }
+
+    let normal_pct = |e : &Entry| (e.total_credits as f64) / max_total_credits;
+
+    let timely_pct = |e : &Entry| (e.total_timely_credits as f64) / max_total_timely_credits;
+
+    // Rank by normal credits and by timely credits, breaking ties by name so that ranks are deterministic
+    let mut normal_ranking : Vec<&Entry> = entries.iter().collect();
+
+    normal_ranking.sort_by(|a, b| b.total_credits.cmp(&a.total_credits).then(a.name.cmp(&b.name)));
+
+    let mut timely_ranking : Vec<&Entry> = entries.iter().collect();
+
+    timely_ranking.sort_by(|a, b| b.total_timely_credits.cmp(&a.total_timely_credits).then(a.name.cmp(&b.name)));
+
+    let timely_ranks : HashMap<&String, usize> =
+        timely_ranking.iter().enumerate().map(|(rank, e)| (&e.name, rank)).collect();
+
+    let rank_churn =
+        normal_ranking.iter().enumerate().map(|(rank, e)| rank.abs_diff(timely_ranks[&e.name])).sum::<usize>() as f64 /
+            (entries.len() as f64);
+
+    // Entries with no credits (such as validators that never rooted a vote in tower credit mode) have no relative
+    // change, so are left out, as the bootstrap leaves out non-finite values; if none are left, there is no change
+    let mean_delta = |entries : &[&Entry]| {
+        let deltas : Vec<f64> = entries
+            .iter()
+            .map(|e| (timely_pct(e) - normal_pct(e)) / normal_pct(e))
+            .filter(|delta| delta.is_finite())
+            .collect();
+        if deltas.is_empty() {
+            0_f64
+        }
+        else {
+            deltas.iter().sum::<f64>() / (deltas.len() as f64)
+        }
+    };
+
+    let tenth = entries.len().div_ceil(10);
+
+    Ok(SweepOutcome {
+        spread : timely_pct(timely_ranking[0]) - timely_pct(timely_ranking[timely_ranking.len() - 1]),
+        rank_churn,
+        top_delta : mean_delta(&normal_ranking[..tenth]),
+        bottom_delta : mean_delta(&normal_ranking[(normal_ranking.len() - tenth)..])
+    })
+}
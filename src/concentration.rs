@@ -0,0 +1,106 @@
+// Measures of how concentrated a quantity (e.g. share of rewards) is among a set of entities.  Each takes the amount
+// held by each entity, in any units.
+
+// The smallest number of entities that together hold more than a third of the total, i.e. enough to halt consensus if
+// the amounts are stake
+pub fn nakamoto_coefficient(amounts : &[f64]) -> usize
+{
+    let total = amounts.iter().sum::<f64>();
+
+    let mut sorted = amounts.to_vec();
+
+    sorted.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+    let mut held = 0_f64;
+
+    for (i, amount) in sorted.iter().enumerate() {
+        held += amount;
+        if (held * 3_f64) > total {
+            return i + 1;
+        }
+    }
+
+    sorted.len()
+}
+
+// Gini coefficient: 0 when all entities hold the same amount, approaching 1 when one entity holds everything
+pub fn gini_coefficient(amounts : &[f64]) -> f64
+{
+    let total = amounts.iter().sum::<f64>();
+
+    if amounts.is_empty() || total == 0_f64 {
+        return 0_f64;
+    }
+
+    let mut sorted = amounts.to_vec();
+
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let n = sorted.len() as f64;
+
+    // Sum over entities, in increasing order of amount, of (2i - n - 1) * amount, for i from 1 to n
+    let weighted =
+        sorted.iter().enumerate().map(|(i, amount)| ((2_f64 * ((i + 1) as f64)) - n - 1_f64) * amount).sum::<f64>();
+
+    weighted / (n * total)
+}
+
+// Herfindahl-Hirschman index: the sum of the squares of the percentage shares of the entities, from near 0 when the
+// amounts are spread among very many entities to 10,000 when one entity holds everything
+pub fn herfindahl_hirschman_index(amounts : &[f64]) -> f64
+{
+    let total = amounts.iter().sum::<f64>();
+
+    if total == 0_f64 {
+        return 0_f64;
+    }
+
+    amounts.iter().map(|amount| ((amount * 100_f64) / total).powi(2)).sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn nakamoto_coefficient_counts_holders_of_more_than_a_third()
+    {
+        assert_eq!(nakamoto_coefficient(&[34_f64, 33_f64, 33_f64]), 1);
+        assert_eq!(nakamoto_coefficient(&[25_f64, 25_f64, 25_f64, 25_f64]), 2);
+
+        // Exactly a third is not enough
+        assert_eq!(nakamoto_coefficient(&[1_f64, 1_f64, 1_f64]), 2);
+
+        // Order of the amounts does not matter
+        assert_eq!(nakamoto_coefficient(&[10_f64, 20_f64, 10_f64, 60_f64]), 1);
+        assert_eq!(nakamoto_coefficient(&[]), 0);
+    }
+
+    #[test]
+    fn gini_coefficient_of_hand_computed_cases()
+    {
+        assert_eq!(gini_coefficient(&[5_f64, 5_f64, 5_f64, 5_f64]), 0_f64);
+
+        // One of n entities holding everything gives (n - 1) / n
+        assert_eq!(gini_coefficient(&[0_f64, 1_f64, 0_f64, 0_f64]), 0.75);
+
+        // Mean absolute difference 2 * 2 / 4 = 1, divided by twice the mean of 2
+        assert_eq!(gini_coefficient(&[3_f64, 1_f64]), 0.25);
+
+        assert_eq!(gini_coefficient(&[]), 0_f64);
+        assert_eq!(gini_coefficient(&[0_f64, 0_f64]), 0_f64);
+    }
+
+    #[test]
+    fn herfindahl_hirschman_index_of_hand_computed_cases()
+    {
+        assert_eq!(herfindahl_hirschman_index(&[7_f64]), 10000_f64);
+        assert_eq!(herfindahl_hirschman_index(&[1_f64, 1_f64, 1_f64, 1_f64]), 2500_f64);
+
+        // 75% and 25%
+        assert_eq!(herfindahl_hirschman_index(&[3_f64, 1_f64]), 6250_f64);
+
+        assert_eq!(herfindahl_hirschman_index(&[0_f64, 0_f64]), 0_f64);
+    }
+}
@@ -0,0 +1,107 @@
+// Converts vote data between the text form that fetch_data writes and the binary vote stream form (see
+// vote_stream.rs), in whichever direction the input calls for.  Arguments are:
+//
+// INPUT OUTPUT [CLUSTER]
+//
+// INPUT may be compressed with gzip, as the files in "data" are.  Text input is written as a vote stream whose header
+// names CLUSTER (if given), and a vote stream is written as text, compressed with gzip if OUTPUT ends with ".gz".  The
+// output is written to OUTPUT.tmp, which is renamed to OUTPUT once complete.
+
+use fetch_data::records::{self, VoteLine};
+use fetch_data::vote_stream;
+use std::io::{BufWriter, Write};
+
+fn main()
+{
+    let mut args = std::env::args();
+    args.next();
+
+    let input_path = args.next().unwrap_or_else(|| {
+        eprintln!("First argument must be input path");
+        std::process::exit(-1);
+    });
+
+    let output_path = args.next().unwrap_or_else(|| {
+        eprintln!("Second argument must be output path");
+        std::process::exit(-1);
+    });
+
+    let cluster = args.next().unwrap_or_default();
+
+    let temporary_path = format!("{}.tmp", output_path);
+
+    let result = convert(&input_path, &temporary_path, &output_path, &cluster).and_then(|_| {
+        std::fs::rename(&temporary_path, &output_path).map_err(|e| format!("Error writing {}: {}", output_path, e))
+    });
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temporary_path);
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    }
+}
+
+fn convert(
+    input_path : &str,
+    temporary_path : &str,
+    output_path : &str,
+    cluster : &str
+) -> Result<(), String>
+{
+    let input = records::open(std::path::Path::new(input_path))?;
+
+    let (is_vote_stream, input) =
+        vote_stream::detect(input).map_err(|e| format!("Error reading {}: {}", input_path, e))?;
+
+    let file =
+        std::fs::File::create(temporary_path).map_err(|e| format!("Error creating {}: {}", temporary_path, e))?;
+
+    let write_error = |e : std::io::Error| format!("Error writing {}: {}", temporary_path, e);
+
+    if is_vote_stream {
+        let lines = vote_stream::read_lines(vote_stream::Reader::new(input)?);
+        if output_path.ends_with(".gz") {
+            let mut out = flate2::write::GzEncoder::new(BufWriter::new(file), flate2::Compression::default());
+            write_text(lines, &mut out, temporary_path)?;
+            out.finish().and_then(|mut out| out.flush()).map_err(write_error)
+        }
+        else {
+            let mut out = BufWriter::new(file);
+            write_text(lines, &mut out, temporary_path)?;
+            out.flush().map_err(write_error)
+        }
+    }
+    else {
+        let mut lines = records::read::<VoteLine, _>(input, input_path);
+        let header = match lines.next() {
+            Some(Ok(VoteLine::Header(header))) => header,
+            Some(Err(e)) => return Err(e),
+            _ => return Err(format!("{} does not start with a # EPOCH FIRST_SLOT LAST_SLOT header", input_path))
+        };
+        let header = vote_stream::StreamHeader {
+            cluster : cluster.to_string(),
+            epoch : header.epoch,
+            first_slot : header.first_slot,
+            last_slot : header.last_slot,
+            tool_version : env!("CARGO_PKG_VERSION").to_string()
+        };
+        let mut writer = vote_stream::Writer::new(BufWriter::new(file), &header).map_err(write_error)?;
+        for line in lines {
+            writer.write_line(&line?)?;
+        }
+        writer.get_mut().flush().map_err(write_error)
+    }
+}
+
+fn write_text(
+    lines : impl Iterator<Item = Result<VoteLine, String>>,
+    out : &mut impl Write,
+    path : &str
+) -> Result<(), String>
+{
+    for line in lines {
+        writeln!(out, "{}", line?).map_err(|e| format!("Error writing {}: {}", path, e))?;
+    }
+
+    Ok(())
+}
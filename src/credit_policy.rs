@@ -0,0 +1,314 @@
+// Policies giving the number of vote credits earned by a vote that landed at a given latency.  Latency here is as
+// written by process_data: 0 means the vote landed as early as possible.  A policy is selected on the command line with
+// an argument of the form:
+
+// linear:GRACE:MAX_CREDITS:MULTIPLIER       -- Latencies 0 through GRACE - 1 earn MAX_CREDITS, and each slot of
+//                                              latency beyond that reduces credits by MULTIPLIER, down to a minimum of
+//                                              1 credit
+// exponential:GRACE:MAX_CREDITS:DECAY       -- Latencies 0 through GRACE - 1 earn MAX_CREDITS, and each slot of
+//                                              latency beyond that multiplies credits by DECAY, down to a minimum of 1
+//                                              credit
+// step:LATENCY=CREDITS,LATENCY=CREDITS,...  -- Latencies up to and including the first LATENCY earn its CREDITS,
+//                                              latencies after that up to and including the next LATENCY earn its
+//                                              CREDITS, and so on; latencies beyond the last LATENCY earn 1 credit
+// table:PATH                                -- PATH is a file of whitespace separated credits, the first for latency
+//                                              0, the second for latency 1, and so on; latencies beyond the end of the
+//                                              table earn the last credits in the table
+// agave                                     -- The timely vote credits policy that Agave shipped: grace 2 and max
+//                                              credits 16, reduced by 1 per slot of latency beyond that
+
+pub trait CreditPolicy
+{
+    fn credits(
+        &self,
+        latency : u64
+    ) -> u64;
+}
+
+struct Linear
+{
+    grace : u64,
+
+    max_credits : u64,
+
+    multiplier : f64
+}
+
+impl CreditPolicy for Linear
+{
+    fn credits(
+        &self,
+        latency : u64
+    ) -> u64
+    {
+        // values of latency from 0 to (grace - 1) (inclusive) should get full credits
+        let reduction = (((std::cmp::max(latency, self.grace - 1) - (self.grace - 1)) as f64) * self.multiplier) as u64;
+
+        if self.max_credits > reduction {
+            self.max_credits - reduction
+        }
+        else {
+            1
+        }
+    }
+}
+
+struct Exponential
+{
+    grace : u64,
+
+    max_credits : u64,
+
+    decay : f64
+}
+
+impl CreditPolicy for Exponential
+{
+    fn credits(
+        &self,
+        latency : u64
+    ) -> u64
+    {
+        let late_slots = std::cmp::max(latency, self.grace - 1) - (self.grace - 1);
+
+        std::cmp::max(((self.max_credits as f64) * self.decay.powf(late_slots as f64)) as u64, 1)
+    }
+}
+
+struct Step
+{
+    // (last latency, credits) in order of increasing latency
+    steps : Vec<(u64, u64)>
+}
+
+impl CreditPolicy for Step
+{
+    fn credits(
+        &self,
+        latency : u64
+    ) -> u64
+    {
+        self.steps.iter().find(|(last_latency, _)| latency <= *last_latency).map(|(_, credits)| *credits).unwrap_or(1)
+    }
+}
+
+struct Table
+{
+    // Credits indexed by latency
+    credits : Vec<u64>
+}
+
+impl CreditPolicy for Table
+{
+    fn credits(
+        &self,
+        latency : u64
+    ) -> u64
+    {
+        *self.credits.get(latency as usize).unwrap_or_else(|| self.credits.last().unwrap())
+    }
+}
+
+// Agave's VOTE_CREDITS_GRACE_SLOTS and VOTE_CREDITS_MAXIMUM_PER_SLOT.  Agave measures latency as landing slot - voted
+// slot, which is one more than the latency used here, so its grace of 2 slots covers latencies 0 and 1.
+const AGAVE_GRACE : u64 = 2;
+
+const AGAVE_MAX_CREDITS : u64 = 16;
+
+// Parses a policy from its command line form
+pub fn parse(spec : &str) -> Result<Box<dyn CreditPolicy>, String>
+{
+    let (name, params) = spec.split_once(':').unwrap_or((spec, ""));
+
+    match name {
+        "linear" => {
+            let (grace, max_credits, multiplier) = parse_curve(spec, params)?;
+            Ok(Box::new(Linear { grace, max_credits, multiplier }))
+        },
+        "exponential" => {
+            let (grace, max_credits, decay) = parse_curve(spec, params)?;
+            if !(decay > 0_f64 && decay <= 1_f64) {
+                return Err(format!("Invalid credit policy {}: decay must be greater than 0 and at most 1", spec));
+            }
+            Ok(Box::new(Exponential { grace, max_credits, decay }))
+        },
+        "step" => {
+            let steps = params
+                .split(',')
+                .map(|step| {
+                    let (last_latency, credits) =
+                        step.split_once('=').ok_or(format!("Invalid step {} in credit policy {}", step, spec))?;
+                    Ok((parse_param(spec, "step latency", last_latency)?, parse_param(spec, "step credits", credits)?))
+                })
+                .collect::<Result<Vec<(u64, u64)>, String>>()?;
+            if steps.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                return Err(format!("Invalid credit policy {}: step latencies must be increasing", spec));
+            }
+            Ok(Box::new(Step { steps }))
+        },
+        "table" => {
+            let contents =
+                std::fs::read_to_string(params).map_err(|e| format!("Error reading credit table {}: {}", params, e))?;
+            let credits = contents
+                .split_whitespace()
+                .map(|credits| parse_param(spec, "table credits", credits))
+                .collect::<Result<Vec<u64>, String>>()?;
+            if credits.is_empty() {
+                return Err(format!("Empty credit table {}", params));
+            }
+            Ok(Box::new(Table { credits }))
+        },
+        "agave" if params.is_empty() => {
+            Ok(Box::new(Linear { grace : AGAVE_GRACE, max_credits : AGAVE_MAX_CREDITS, multiplier : 1_f64 }))
+        },
+        _ => Err(format!(
+            "Invalid credit policy {} (must be linear:GRACE:MAX_CREDITS:MULTIPLIER, \
+             exponential:GRACE:MAX_CREDITS:DECAY, step:LATENCY=CREDITS,..., table:PATH, or agave)",
+            spec
+        ))
+    }
+}
+
+// Parses the GRACE:MAX_CREDITS:FACTOR parameters shared by the linear and exponential policies
+fn parse_curve(
+    spec : &str,
+    params : &str
+) -> Result<(u64, u64, f64), String>
+{
+    let split : Vec<&str> = params.split(':').collect();
+
+    if split.len() != 3 {
+        return Err(format!("Invalid credit policy {}: expected GRACE:MAX_CREDITS:FACTOR", spec));
+    }
+
+    let grace = parse_param(spec, "grace", split[0])?;
+
+    if grace == 0 {
+        return Err(format!("Invalid credit policy {}: grace must be at least 1", spec));
+    }
+
+    let max_credits = parse_param(spec, "max credits", split[1])?;
+
+    // Credits never fall below 1, so a max of 0 would give 1 credit at every latency
+    if max_credits == 0 {
+        return Err(format!("Invalid credit policy {}: max credits must be at least 1", spec));
+    }
+
+    Ok((grace, max_credits, parse_param(spec, "factor", split[2])?))
+}
+
+fn parse_param<T : std::str::FromStr>(
+    spec : &str,
+    what : &str,
+    value : &str
+) -> Result<T, String>
+where
+    T::Err : std::fmt::Display
+{
+    value.parse::<T>().map_err(|e| format!("Invalid {} {} in credit policy {}: {}", what, value, spec, e))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn credits(
+        spec : &str,
+        latencies : &[u64]
+    ) -> Vec<u64>
+    {
+        let policy = parse(spec).unwrap();
+        latencies.iter().map(|latency| policy.credits(*latency)).collect()
+    }
+
+    // Writes a credit table with the given contents to a temporary file, returning its path
+    fn table(
+        name : &str,
+        contents : &str
+    ) -> String
+    {
+        let path = std::env::temp_dir().join(format!("credit_policy_test_{}_{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn linear_credits()
+    {
+        // Full credits through grace - 1, then 1 less per slot, down to the floor of 1
+        assert_eq!(credits("linear:2:16:1", &[0, 1, 2, 3, 16, 17, 100]), vec![16, 16, 15, 14, 1, 1, 1]);
+        assert_eq!(credits("linear:3:10:2", &[2, 3, 4, 7, 100]), vec![10, 8, 6, 1, 1]);
+    }
+
+    #[test]
+    fn exponential_credits()
+    {
+        assert_eq!(credits("exponential:2:16:0.5", &[0, 1, 2, 3, 5, 6, 100]), vec![16, 16, 8, 4, 1, 1, 1]);
+    }
+
+    #[test]
+    fn step_credits()
+    {
+        assert_eq!(credits("step:1=16,4=8", &[0, 1, 2, 4, 5, 100]), vec![16, 16, 8, 8, 1, 1]);
+    }
+
+    #[test]
+    fn table_credits()
+    {
+        let path = table("credits", "16 12\n8\n");
+        let result = credits(&format!("table:{}", path), &[0, 1, 2, 3, 100]);
+        std::fs::remove_file(&path).unwrap();
+
+        // Latencies beyond the end of the table earn its last credits rather than 1
+        assert_eq!(result, vec![16, 12, 8, 8, 8]);
+    }
+
+    #[test]
+    fn agave_is_linear_2_16_1()
+    {
+        let latencies : Vec<u64> = (0..(crate::records::LATENCIES as u64)).collect();
+
+        assert_eq!(credits("agave", &latencies), credits("linear:2:16:1", &latencies));
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected()
+    {
+        for spec in [
+            "linear:0:16:1",
+            "linear:2:0:1",
+            "linear:2:16",
+            "linear:2:x:1",
+            "exponential:0:16:0.5",
+            "exponential:2:16:0",
+            "exponential:2:16:1.5",
+            "step:4=8,1=16",
+            "step:1=16,1=8",
+            "step:1:16",
+            "agave:1",
+            "quadratic:2:16:1"
+        ] {
+            assert!(parse(spec).is_err(), "{} was accepted", spec);
+        }
+    }
+
+    #[test]
+    fn invalid_tables_are_rejected()
+    {
+        let empty = table("empty", " \n");
+        let bad = table("bad", "16 x 8");
+        let missing = std::env::temp_dir().join(format!("credit_policy_test_{}_missing", std::process::id()));
+
+        let results = [
+            parse(&format!("table:{}", empty)).is_err(),
+            parse(&format!("table:{}", bad)).is_err(),
+            parse(&format!("table:{}", missing.to_str().unwrap())).is_err()
+        ];
+
+        std::fs::remove_file(&empty).unwrap();
+        std::fs::remove_file(&bad).unwrap();
+
+        assert_eq!(results, [true, true, true]);
+    }
+}
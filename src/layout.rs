@@ -0,0 +1,71 @@
+// The directories that the pipeline keeps its files in, relative to the directory it is run in, each holding one file
+// per epoch (see README.txt).
+
+use std::path::Path;
+
+// Vote data written by fetch_data, EPOCH.gz (along with EPOCH.state, the vote account state at the end of the epoch),
+// and EPOCH.votes if converted to a vote stream
+pub const DATA : &str = "data";
+
+// Suffix of the files of vote data that are vote streams (see vote_stream.rs)
+pub const VOTE_STREAM_SUFFIX : &str = ".votes";
+
+// Slot metadata written by fetch_data, EPOCH.gz
+pub const SLOTS : &str = "data_slots";
+
+// Gap reports written by fetch_data
+pub const GAPS : &str = "data_gaps";
+
+// Activated stakes and commissions written by fetch_stakes.sh
+pub const STAKES : &str = "data_stakes";
+
+pub const COMMISSIONS : &str = "data_commissions";
+
+// Per-validator histograms, failures, and blocks of slots written by process_data
+pub const PROCESSED : &str = "data_processed";
+
+pub const FAILURES : &str = "data_failures";
+
+pub const BLOCKS : &str = "data_blocks";
+
+// The simulated vote towers of every vote account at the end of each epoch, written by process_data in tower credit
+// mode, from which the next epoch's towers start
+pub const TOWERS : &str = "data_towers";
+
+// Results written by calculate_results, in a subdirectory per credit policy
+pub const RESULTS : &str = "results";
+
+// Sweeps of the linear credit policy over all processed epochs written by calculate_results, one file per level
+pub const SWEEPS : &str = "sweeps";
+
+// HTML pages written by collate_results, in a subdirectory per epoch and one for the average over epochs
+pub const HTML : &str = "timely_voting_proposal";
+
+// Lists the epochs of a directory of per-epoch files named EPOCH followed by the given suffix (e.g. ".gz" for DATA, or
+// "" for PROCESSED), in increasing order
+pub fn list_epochs(
+    directory : &Path,
+    suffix : &str
+) -> Result<Vec<u64>, String>
+{
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        // Nothing fetched or processed yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("Error reading {}: {}", directory.display(), e))
+    };
+
+    let mut epochs = vec![];
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Error reading {}: {}", directory.display(), e))?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if let Some(Ok(epoch)) = file_name.strip_suffix(suffix).map(|epoch| epoch.parse::<u64>()) {
+            epochs.push(epoch);
+        }
+    }
+
+    epochs.sort();
+
+    Ok(epochs)
+}
@@ -0,0 +1,318 @@
+// Sources of confirmed blocks for fetch_data.  A ledger source is selected on the command line with an argument of
+// the form:
+
+// bigtable:CREDENTIALS_PATH  -- Google Bigtables, using the given credentials file
+// rpc:URL                    -- The getBlock JSON-RPC method of a validator, e.g. solana-test-validator
+// dir:PATH                   -- A directory of files named SLOT.json, each containing the result of getBlock for that
+//                               slot with "base64" transaction encoding
+
+// An argument without one of these prefixes is a Bigtable credentials path.
+
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    ConfirmedBlock, TransactionDetails, TransactionStatusMeta, TransactionWithStatusMeta, UiConfirmedBlock,
+    UiLoadedAddresses, UiTransactionEncoding, VersionedTransactionWithStatusMeta
+};
+use std::str::FromStr;
+
+pub trait LedgerSource
+{
+    // Returns the slots from first_slot to last_slot (inclusive) which have a confirmed block according to the ledger
+    // source's index of blocks, in slot order.  This may include slots for which get_blocks nonetheless has no data.
+    async fn get_confirmed_slots(
+        &self,
+        first_slot : u64,
+        last_slot : u64
+    ) -> Result<Vec<u64>, String>;
+
+    // Returns the confirmed blocks within the given slots, in slot order.  Slots which have no confirmed block are
+    // omitted.
+    async fn get_blocks(
+        &self,
+        slots : &[u64]
+    ) -> Result<Vec<(u64, ConfirmedBlock)>, String>;
+}
+
+pub enum LedgerSourceArg
+{
+    Bigtable(String),
+
+    Rpc(String),
+
+    Dir(String)
+}
+
+impl FromStr for LedgerSourceArg
+{
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err>
+    {
+        match s.split_once(':') {
+            Some(("bigtable", credentials_path)) => Ok(LedgerSourceArg::Bigtable(credentials_path.to_string())),
+            Some(("rpc", url)) => Ok(LedgerSourceArg::Rpc(url.to_string())),
+            Some(("dir", path)) => Ok(LedgerSourceArg::Dir(path.to_string())),
+            _ if s.is_empty() => Err("Empty ledger source".to_string()),
+            _ => Ok(LedgerSourceArg::Bigtable(s.to_string()))
+        }
+    }
+}
+
+pub struct BigtableLedgerSource
+{
+    ledger_storage : solana_storage_bigtable::LedgerStorage
+}
+
+impl BigtableLedgerSource
+{
+    pub async fn new(credentials_path : &str) -> Result<Self, String>
+    {
+        solana_storage_bigtable::LedgerStorage::new(true, None, Some(credentials_path.to_string()))
+            .await
+            .map(|ledger_storage| BigtableLedgerSource { ledger_storage })
+            .map_err(|e| format!("Failed to connect to Bigtable using {}: {}", credentials_path, e))
+    }
+}
+
+impl LedgerSource for BigtableLedgerSource
+{
+    async fn get_confirmed_slots(
+        &self,
+        first_slot : u64,
+        last_slot : u64
+    ) -> Result<Vec<u64>, String>
+    {
+        self.ledger_storage
+            .get_confirmed_blocks(first_slot, ((last_slot - first_slot) + 1) as usize)
+            .await
+            .map(|slots| slots.into_iter().filter(|slot| *slot <= last_slot).collect())
+            .map_err(|e| format!("Failed to fetch confirmed slots {} - {} from Bigtable: {}", first_slot, last_slot, e))
+    }
+
+    async fn get_blocks(
+        &self,
+        slots : &[u64]
+    ) -> Result<Vec<(u64, ConfirmedBlock)>, String>
+    {
+        self.ledger_storage
+            .get_confirmed_blocks_with_data(slots)
+            .await
+            .map(|blocks| blocks.collect())
+            .map_err(|e| format!("Failed to fetch blocks from Bigtable: {}", e))
+    }
+}
+
+pub struct RpcLedgerSource
+{
+    rpc_client : solana_rpc_client::nonblocking::rpc_client::RpcClient
+}
+
+impl RpcLedgerSource
+{
+    pub fn new(url : &str) -> Self
+    {
+        RpcLedgerSource {
+            rpc_client : solana_rpc_client::nonblocking::rpc_client::RpcClient::new_with_commitment(
+                url.to_string(),
+                solana_sdk::commitment_config::CommitmentConfig::confirmed()
+            )
+        }
+    }
+}
+
+impl LedgerSource for RpcLedgerSource
+{
+    async fn get_confirmed_slots(
+        &self,
+        first_slot : u64,
+        last_slot : u64
+    ) -> Result<Vec<u64>, String>
+    {
+        self.rpc_client
+            .get_blocks(first_slot, Some(last_slot))
+            .await
+            .map_err(|e| format!("Failed to fetch confirmed slots {} - {}: {}", first_slot, last_slot, e))
+    }
+
+    async fn get_blocks(
+        &self,
+        slots : &[u64]
+    ) -> Result<Vec<(u64, ConfirmedBlock)>, String>
+    {
+        let (first_slot, last_slot) = match (slots.first(), slots.last()) {
+            (Some(first_slot), Some(last_slot)) => (*first_slot, *last_slot),
+            _ => return Ok(vec![])
+        };
+
+        // Ask for the slots that have blocks first, so that skipped slots are not requested
+        let confirmed_slots = self.get_confirmed_slots(first_slot, last_slot).await?;
+
+        let mut blocks = vec![];
+
+        for slot in confirmed_slots.into_iter().filter(|slot| slots.contains(slot)) {
+            let block = match self
+                .rpc_client
+                .get_block_with_config(slot, solana_rpc_client_api::config::RpcBlockConfig {
+                    encoding : Some(UiTransactionEncoding::Base64),
+                    transaction_details : Some(TransactionDetails::Full),
+                    rewards : Some(true),
+                    commitment : Some(solana_sdk::commitment_config::CommitmentConfig::confirmed()),
+                    max_supported_transaction_version : Some(0)
+                })
+                .await
+            {
+                Ok(block) => block,
+                // The node not having the block means no data for that slot, as for a missing file of DirLedgerSource
+                Err(e) if is_block_unavailable(&e) => continue,
+                Err(e) => return Err(format!("Failed to fetch block {}: {}", slot, e))
+            };
+
+            blocks.push((slot, decode_block(slot, block)?));
+        }
+
+        Ok(blocks)
+    }
+}
+
+// Whether a getBlock error is the node reporting that it has no block for the slot, rather than a failure to talk to
+// the node
+fn is_block_unavailable(error : &solana_rpc_client_api::client_error::Error) -> bool
+{
+    use solana_rpc_client_api::custom_error::*;
+
+    match error.kind() {
+        solana_rpc_client_api::client_error::ErrorKind::RpcError(
+            solana_rpc_client_api::request::RpcError::RpcResponseError { code, .. }
+        ) => [
+            JSON_RPC_SERVER_ERROR_BLOCK_CLEANED_UP,
+            JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+            JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+            JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED
+        ]
+        .contains(code),
+        _ => false
+    }
+}
+
+pub struct DirLedgerSource
+{
+    path : std::path::PathBuf
+}
+
+impl DirLedgerSource
+{
+    pub fn new(path : &str) -> Self
+    {
+        DirLedgerSource { path : std::path::PathBuf::from(path) }
+    }
+}
+
+impl LedgerSource for DirLedgerSource
+{
+    // The directory has no index of blocks other than the block files themselves
+    async fn get_confirmed_slots(
+        &self,
+        first_slot : u64,
+        last_slot : u64
+    ) -> Result<Vec<u64>, String>
+    {
+        Ok((first_slot..=last_slot).filter(|slot| self.path.join(format!("{}.json", slot)).is_file()).collect())
+    }
+
+    async fn get_blocks(
+        &self,
+        slots : &[u64]
+    ) -> Result<Vec<(u64, ConfirmedBlock)>, String>
+    {
+        let mut blocks = vec![];
+
+        for slot in slots {
+            let path = self.path.join(format!("{}.json", slot));
+
+            let file = match std::fs::File::open(&path) {
+                Ok(file) => file,
+                // No file means no block in that slot
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Error opening {}: {}", path.display(), e))
+            };
+
+            let block : UiConfirmedBlock = serde_json::from_reader(std::io::BufReader::new(file))
+                .map_err(|e| format!("Error reading block JSON from {}: {}", path.display(), e))?;
+
+            blocks.push((*slot, decode_block(*slot, block)?));
+        }
+
+        Ok(blocks)
+    }
+}
+
+// Converts a block in the getBlock JSON-RPC representation into the form returned by Bigtable.  Only the parts of
+// the transaction status meta that are present in the JSON representation and which fetch_data uses are kept.
+fn decode_block(
+    slot : u64,
+    block : UiConfirmedBlock
+) -> Result<ConfirmedBlock, String>
+{
+    let transactions = block
+        .transactions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|encoded| {
+            let transaction = encoded
+                .transaction
+                .decode()
+                .ok_or(format!("Undecodable transaction in slot {} (blocks must use base64 encoding)", slot))?;
+            match encoded.meta {
+                Some(meta) => {
+                    let loaded_addresses = match Option::<UiLoadedAddresses>::from(meta.loaded_addresses) {
+                        Some(loaded_addresses) => solana_sdk::message::v0::LoadedAddresses {
+                            writable : parse_pubkeys(slot, &loaded_addresses.writable)?,
+                            readonly : parse_pubkeys(slot, &loaded_addresses.readonly)?
+                        },
+                        None => Default::default()
+                    };
+                    Ok(TransactionWithStatusMeta::Complete(VersionedTransactionWithStatusMeta {
+                        transaction,
+                        meta : TransactionStatusMeta {
+                            status : meta.status,
+                            fee : meta.fee,
+                            pre_balances : meta.pre_balances,
+                            post_balances : meta.post_balances,
+                            rewards : meta.rewards.into(),
+                            loaded_addresses,
+                            compute_units_consumed : meta.compute_units_consumed.into(),
+                            ..TransactionStatusMeta::default()
+                        }
+                    }))
+                },
+                None => transaction
+                    .into_legacy_transaction()
+                    .map(TransactionWithStatusMeta::MissingMetadata)
+                    .ok_or(format!("Versioned transaction without metadata in slot {}", slot))
+            }
+        })
+        .collect::<Result<Vec<TransactionWithStatusMeta>, String>>()?;
+
+    Ok(ConfirmedBlock {
+        previous_blockhash : block.previous_blockhash,
+        blockhash : block.blockhash,
+        parent_slot : block.parent_slot,
+        transactions,
+        rewards : block.rewards.unwrap_or_default(),
+        block_time : block.block_time,
+        block_height : block.block_height
+    })
+}
+
+fn parse_pubkeys(
+    slot : u64,
+    pubkeys : &[String]
+) -> Result<Vec<Pubkey>, String>
+{
+    pubkeys
+        .iter()
+        .map(|pubkey| {
+            Pubkey::from_str(pubkey).map_err(|e| format!("Invalid pubkey {} in slot {}: {}", pubkey, slot, e))
+        })
+        .collect()
+}
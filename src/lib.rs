@@ -0,0 +1,16 @@
+// Code shared by the programs of the pipeline, which other tools can be built on too: typed records of the lines of the
+// files that pass between fetch_data, process_data, calculate_results, and collate_results (see records.rs), the
+// validators.app details that give the data centers, names, and icons of validators (see validators.rs), the credit
+// policies (see credit_policy.rs), the directories that the pipeline keeps its files in (see layout.rs), running the
+// programs from one another (see runner.rs), the manifests that record what each output was made from (see
+// manifest.rs), the binary form of fetch_data's vote data (see vote_stream.rs), and the scenario files that describe
+// a whole run of the pipeline (see scenario.rs).
+
+pub mod credit_policy;
+pub mod layout;
+pub mod manifest;
+pub mod records;
+pub mod runner;
+pub mod scenario;
+pub mod validators;
+pub mod vote_stream;
@@ -1,45 +1,318 @@
-// Connect to google bigtables using a credentials file
+// Connect to a ledger source: google bigtables using a credentials file, a validator's JSON-RPC, or a directory of
+// JSON blocks (see ledger_source.rs)
 
-// Download blocks in a given range of slots.  For each block, find vote transactions.  Parse them out.  Write
-// out the results in this format:
+// Download blocks in the slots of a given epoch.  For each block, find vote transactions.  Parse them out.  Write
+// out the results in this format, following a first line of the form "# EPOCH FIRST_SLOT LAST_SLOT":
 
-// slot#  vote_account  voted_on 1|0 (1=success, 0=failure)
+// slot#  vote_account  voted_on...  (for a vote transaction that succeeded)
+// slot#  vote_account  !error_kind  (for a vote transaction that failed, with the kind of error that it failed with)
 
+// The results are written to stdout, or to an output file if one is given.  When writing to an output file, a
+// checkpoint (OUTPUT_FILE.checkpoint) is written periodically, and if fetch_data is run again after being interrupted,
+// it resumes from that checkpoint, appending to the output file.  Failed fetches are retried with exponential backoff.
+// If the output file's name ends with ".votes", the results are instead written as a binary vote stream (see
+// vote_stream.rs), whose header names the cluster given as the optional last argument.
+
+// If a slot metadata file is also given, a line is written to it for every block, following the same header line:
+
+// slot#  parent_slot  block_height  block_time  leader  vote_transactions  non_vote_transactions
+
+// where block_height, block_time and leader (the identity that was paid the block's fees) are _ if not known.
+
+// If a gaps file is also given, the slots that the ledger source lists as having a block, but for which it returned no
+// data, are written to it, so that missing data can be told apart from skipped slots.  Following the same header
+// line, it has a line for every batch of slots fetched:
+
+// first_slot  last_slot  confirmed_slots  fetched_slots  missing_slot...
+
+// The epoch's slots are determined by an epoch schedule argument, which may be any of:
+
+// mainnet            -- 432000 slots per epoch without warmup, as on mainnet-beta
+// SLOTS_PER_EPOCH    -- the given number of slots per epoch without warmup
+// warmup:SLOTS       -- the given number of slots per epoch, with warmup epochs
+// PATH               -- a ledger directory containing the cluster's genesis.bin, or a JSON file containing the
+//                       result of the getEpochSchedule JSON-RPC method
+
+mod ledger_source;
+mod state;
+
+use fetch_data::layout;
+use fetch_data::records::{EpochHeader, VoteLine};
+use fetch_data::vote_stream;
+use ledger_source::{BigtableLedgerSource, DirLedgerSource, LedgerSource, LedgerSourceArg, RpcLedgerSource};
+use solana_sdk::epoch_schedule::{EpochSchedule, MINIMUM_SLOTS_PER_EPOCH};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+use solana_transaction_status::ConfirmedBlock;
+use solana_vote_program::vote_error::VoteError;
 use solana_vote_program::vote_instruction::VoteInstruction;
-use std::collections::HashMap;
+use state::{load_vote_account_state, write_vote_account_state, Checkpoint};
+use std::io::{Seek, Write};
 use std::str::FromStr;
+use std::time::Duration;
+
+// Instruction discriminants of the TowerSync vote instructions, which are newer than the vote program version used
+// here.  TowerSync is encoded exactly as CompactUpdateVoteState with a block id appended, and TowerSyncSwitch is the
+// same with the switch proof hash appended after that, so they are decoded as their CompactUpdateVoteState
+// counterparts with the trailing bytes ignored.
+const TOWER_SYNC : u32 = 14;
+const TOWER_SYNC_SWITCH : u32 = 15;
+const COMPACT_UPDATE_VOTE_STATE : u32 = 12;
+const COMPACT_UPDATE_VOTE_STATE_SWITCH : u32 = 13;
+
+// Number of slots requested from the ledger source at a time
+const BATCH_SLOTS : u64 = 100;
+
+// Number of batches between checkpoints
+const CHECKPOINT_BATCHES : u64 = 10;
+
+// Number of times a batch is requested before giving up, and the delays between those requests
+const MAX_ATTEMPTS : u32 = 10;
+const INITIAL_BACKOFF : Duration = Duration::from_secs(1);
+const MAX_BACKOFF : Duration = Duration::from_secs(120);
 
 struct Args
 {
-    credentials_path : String,
+    ledger_source : LedgerSourceArg,
+
+    epoch : u64,
 
     first_slot : u64,
 
-    last_slot : u64
+    last_slot : u64,
+
+    // If present, the file from which the last landed slot of each vote account is loaded at startup (if it exists)
+    // and to which the final state is written on exit
+    state_path : Option<String>,
+
+    // If present, the file to write results to instead of stdout
+    output_path : Option<String>,
+
+    // If present, the file to write slot metadata to
+    slots_path : Option<String>,
+
+    // If present, the file to write the gap report to
+    gaps_path : Option<String>,
+
+    // Name of the cluster, for the header of a binary vote stream (empty if not known)
+    cluster : String
+}
+
+// Where results are written
+enum Output
+{
+    Stdout(std::io::Stdout),
+
+    File(std::io::BufWriter<std::fs::File>)
+}
+
+impl Write for Output
+{
+    fn write(
+        &mut self,
+        buf : &[u8]
+    ) -> std::io::Result<usize>
+    {
+        match self {
+            Output::Stdout(stdout) => stdout.write(buf),
+            Output::File(file) => file.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        match self {
+            Output::Stdout(stdout) => stdout.flush(),
+            Output::File(file) => file.flush()
+        }
+    }
 }
 
 fn parse_args() -> Result<Args, String>
 {
     let mut args = std::env::args();
 
-    args.nth(0);
+    args.next();
 
-    // credentials_path is first arg
-    let credentials_path = args.nth(0).ok_or("First argument must be credentials file path".to_string())?;
+    // ledger_source is first arg
+    let ledger_source = args
+        .next()
+        .ok_or("First argument must be ledger source".to_string())?
+        .parse::<LedgerSourceArg>()
+        .map_err(|e| format!("First argument must be ledger source: {}", e))?;
 
-    let first_slot = args
-        .nth(0)
-        .ok_or("Second argument must be first slot to fetch".to_string())?
-        .parse::<u64>()
-        .map_err(|e| format!("Second argument must be first slot to fetch: {}", e))?;
+    let epoch_schedule = args
+        .next()
+        .ok_or("Second argument must be epoch schedule".to_string())
+        .and_then(|arg| load_epoch_schedule(&arg))
+        .map_err(|e| format!("Second argument must be epoch schedule: {}", e))?;
 
-    let last_slot = args
-        .nth(0)
-        .ok_or("Third argument must be last slot to fetch".to_string())?
+    let epoch = args
+        .next()
+        .ok_or("Third argument must be epoch to fetch".to_string())?
         .parse::<u64>()
-        .map_err(|e| format!("Third argument must be last slot to fetch: {}", e))?;
+        .map_err(|e| format!("Third argument must be epoch to fetch: {}", e))?;
 
-    Ok(Args { credentials_path, first_slot, last_slot })
+    let first_slot = epoch_schedule.get_first_slot_in_epoch(epoch);
+
+    let last_slot = epoch_schedule.get_last_slot_in_epoch(epoch);
+
+    // state_path is optional fourth arg
+    let state_path = args.next();
+
+    // output_path is optional fifth arg
+    let output_path = args.next();
+
+    // slots_path is optional sixth arg
+    let slots_path = args.next();
+
+    // gaps_path is optional seventh arg
+    let gaps_path = args.next();
+
+    // cluster is optional eighth arg
+    let cluster = args.next().unwrap_or_default();
+
+    Ok(Args { ledger_source, epoch, first_slot, last_slot, state_path, output_path, slots_path, gaps_path, cluster })
+}
+
+fn load_epoch_schedule(arg : &str) -> Result<EpochSchedule, String>
+{
+    if arg == "mainnet" {
+        return Ok(EpochSchedule::without_warmup());
+    }
+
+    if let Ok(slots_per_epoch) = arg.parse::<u64>() {
+        check_slots_per_epoch(slots_per_epoch)?;
+        return Ok(EpochSchedule::custom(slots_per_epoch, slots_per_epoch, false));
+    }
+
+    if let Some(slots_per_epoch) = arg.strip_prefix("warmup:") {
+        let slots_per_epoch = slots_per_epoch
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid slots per epoch {}: {}", slots_per_epoch, e))?;
+        check_slots_per_epoch(slots_per_epoch)?;
+        return Ok(EpochSchedule::custom(slots_per_epoch, slots_per_epoch, true));
+    }
+
+    let path = std::path::Path::new(arg);
+
+    let epoch_schedule : EpochSchedule = if path.is_dir() {
+        solana_sdk::genesis_config::GenesisConfig::load(path)
+            .map(|genesis_config| genesis_config.epoch_schedule)
+            .map_err(|e| format!("Error loading genesis config from {}: {}", arg, e))?
+    }
+    else {
+        std::fs::File::open(path).map_err(|e| format!("Error opening epoch schedule file {}: {}", arg, e)).and_then(
+            |file| {
+                serde_json::from_reader(std::io::BufReader::new(file))
+                    .map_err(|e| format!("Error reading epoch schedule JSON from {}: {}", arg, e))
+            }
+        )?
+    };
+
+    check_slots_per_epoch(epoch_schedule.slots_per_epoch)
+        .map_err(|e| format!("Invalid epoch schedule in {}: {}", arg, e))?;
+
+    Ok(epoch_schedule)
+}
+
+// Epoch schedules with fewer slots per epoch than the minimum can't be made, and would divide by zero if 0
+fn check_slots_per_epoch(slots_per_epoch : u64) -> Result<(), String>
+{
+    if slots_per_epoch < MINIMUM_SLOTS_PER_EPOCH {
+        Err(format!("Slots per epoch {} is below the minimum of {}", slots_per_epoch, MINIMUM_SLOTS_PER_EPOCH))
+    }
+    else {
+        Ok(())
+    }
+}
+
+// Returns the slots voted on by the given vote instruction data, or None if it is not a vote instruction.  For the
+// vote state update style instructions, these are the slots of the lockouts in the proposed tower.
+fn voted_slots(data : &[u8]) -> Option<Vec<u64>>
+{
+    let mut data = data.to_vec();
+
+    if data.len() >= 4 {
+        let discriminant = match u32::from_le_bytes([data[0], data[1], data[2], data[3]]) {
+            TOWER_SYNC => COMPACT_UPDATE_VOTE_STATE,
+            TOWER_SYNC_SWITCH => COMPACT_UPDATE_VOTE_STATE_SWITCH,
+            discriminant => discriminant
+        };
+        data[0..4].copy_from_slice(&discriminant.to_le_bytes());
+    }
+
+    match solana_sdk::program_utils::limited_deserialize(&data) {
+        Ok(VoteInstruction::Vote(vote)) | Ok(VoteInstruction::VoteSwitch(vote, _)) => Some(vote.slots),
+        Ok(VoteInstruction::UpdateVoteState(update)) |
+        Ok(VoteInstruction::UpdateVoteStateSwitch(update, _)) |
+        Ok(VoteInstruction::CompactUpdateVoteState(update)) |
+        Ok(VoteInstruction::CompactUpdateVoteStateSwitch(update, _)) => Some(update.slots()),
+        _ => None
+    }
+}
+
+// Returns a single word describing the kind of error that a vote transaction failed with, given the index of its vote
+// instruction.  Custom errors of the vote instruction are given by their VoteError name (e.g. LockoutConflict), and
+// custom errors of other instructions (e.g. of the compute budget program) as Custom followed by the code; everything
+// else by the name of the error variant (e.g. BlockhashNotFound).
+fn error_kind(
+    error : &TransactionError,
+    vote_instruction_index : usize
+) -> String
+{
+    // The Debug output of an error enum begins with its variant name
+    fn variant_name<T : std::fmt::Debug>(t : &T) -> String
+    {
+        format!("{:?}", t).chars().take_while(|c| c.is_alphanumeric()).collect()
+    }
+
+    match error {
+        TransactionError::InstructionError(index, InstructionError::Custom(code))
+            if (*index as usize) == vote_instruction_index =>
+        {
+            match <VoteError as num_traits::FromPrimitive>::from_u32(*code) {
+                Some(vote_error) => variant_name(&vote_error),
+                None => format!("Custom{}", code)
+            }
+        },
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => format!("Custom{}", code),
+        TransactionError::InstructionError(_, instruction_error) => variant_name(instruction_error),
+        _ => variant_name(error)
+    }
+}
+
+// Makes a request of the ledger source, retrying failures with exponential backoff, since a fetch of a whole epoch
+// takes hours and transient errors are to be expected over that time
+async fn with_retries<T, F, Fut>(mut request : F) -> Result<T, String>
+where
+    F : FnMut() -> Fut,
+    Fut : std::future::Future<Output = Result<T, String>>
+{
+    let mut backoff = INITIAL_BACKOFF;
+
+    let mut attempt = 1;
+
+    loop {
+        match request().await {
+            Ok(result) => return Ok(result),
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                eprintln!("{} (attempt {}/{}, retrying in {:?})", e, attempt, MAX_ATTEMPTS, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+                attempt += 1;
+            },
+            Err(e) => return Err(format!("{} (giving up after {} attempts)", e, attempt))
+        }
+    }
+}
+
+fn exit_on_error<T>(result : Result<T, String>) -> T
+{
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    })
 }
 
 #[tokio::main]
@@ -50,81 +323,183 @@ async fn main()
         std::process::exit(-1);
     });
 
+    match &args.ledger_source {
+        LedgerSourceArg::Bigtable(credentials_path) => {
+            let ledger_source = BigtableLedgerSource::new(credentials_path).await.unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(-1);
+            });
+            fetch(&ledger_source, &args).await
+        },
+        LedgerSourceArg::Rpc(url) => fetch(&RpcLedgerSource::new(url), &args).await,
+        LedgerSourceArg::Dir(path) => fetch(&DirLedgerSource::new(path), &args).await
+    }
+}
+
+async fn fetch<S : LedgerSource>(
+    ledger_source : &S,
+    args : &Args
+)
+{
     let vote_program_id = solana_sdk::pubkey::Pubkey::from_str("Vote111111111111111111111111111111111111111").unwrap();
 
-    let ledger_storage =
-        solana_storage_bigtable::LedgerStorage::new(true, None, Some(args.credentials_path.clone())).await.unwrap();
+    let checkpoint_path = args.output_path.as_ref().map(|output_path| format!("{}.checkpoint", output_path));
+
+    let checkpoint = match &checkpoint_path {
+        Some(checkpoint_path) => exit_on_error(Checkpoint::load(checkpoint_path)),
+        None => None
+    };
+
+    // Keep track of the latest landed vote for each vote account, starting from the checkpoint if resuming, or else
+    // from the saved state of the previous fetch if there is one, so that slots which landed before first_slot are
+    // not emitted again
+    let mut vote_account_state = match (&checkpoint, &args.state_path) {
+        (Some(checkpoint), _) => checkpoint.vote_account_state.clone(),
+        (None, Some(state_path)) => exit_on_error(load_vote_account_state(state_path)),
+        (None, None) => Default::default()
+    };
+
+    // When resuming, anything written after the checkpoint was taken is discarded, since it will be written again
+    let out = match &args.output_path {
+        Some(output_path) => open_output(output_path, checkpoint.as_ref().map(|checkpoint| checkpoint.output_length)),
+        None => Output::Stdout(std::io::stdout())
+    };
+
+    let header = EpochHeader { epoch : args.epoch, first_slot : args.first_slot, last_slot : args.last_slot };
+
+    let mut out = match &args.output_path {
+        Some(output_path) if output_path.ends_with(layout::VOTE_STREAM_SUFFIX) => {
+            VoteOutput::Binary(open_vote_stream(out, output_path, &header, &args.cluster, checkpoint.is_some()))
+        },
+        _ => VoteOutput::Text(out)
+    };
 
-    // Keep track of the latest landed vote for each vote account
-    let mut vote_account_state = HashMap::<solana_sdk::pubkey::Pubkey, u64>::new();
+    let mut slots_out = args
+        .slots_path
+        .as_ref()
+        .map(|slots_path| open_output(slots_path, checkpoint.as_ref().map(|checkpoint| checkpoint.slots_length)));
 
-    // Take 100 at a time
-    let mut block = args.first_slot;
+    let mut gaps_out = args
+        .gaps_path
+        .as_ref()
+        .map(|gaps_path| open_output(gaps_path, checkpoint.as_ref().map(|checkpoint| checkpoint.gaps_length)));
+
+    let mut block = match &checkpoint {
+        Some(checkpoint) => {
+            eprintln!("Resuming from checkpoint at slot {}", checkpoint.next_slot);
+            checkpoint.next_slot
+        },
+        None => {
+            if let VoteOutput::Text(out) = &mut out {
+                write_output(out, &header.to_string());
+            }
+            let header = header.to_string();
+            if let Some(slots_out) = &mut slots_out {
+                write_output(slots_out, &header);
+            }
+            if let Some(gaps_out) = &mut gaps_out {
+                write_output(gaps_out, &header);
+            }
+            args.first_slot
+        }
+    };
 
     let total = (args.last_slot - args.first_slot) + 1;
 
+    let mut batches = 0_u64;
+
     loop {
         if block > args.last_slot {
             break;
         }
 
-        let limit = std::cmp::min(100, (args.last_slot - block) + 1);
+        // Take BATCH_SLOTS at a time
+        let limit = std::cmp::min(BATCH_SLOTS, (args.last_slot - block) + 1);
 
         let range : Vec<u64> = (block..(block + limit)).collect();
 
-        for (slot, block) in ledger_storage.get_confirmed_blocks_with_data(&range.as_slice()).await.unwrap() {
-            for result in block.transactions.into_iter().map(|meta| match meta {
+        let blocks = exit_on_error(with_retries(|| ledger_source.get_blocks(range.as_slice())).await);
+
+        if let Some(gaps_out) = &mut gaps_out {
+            let last = (block + limit) - 1;
+            let confirmed_slots = exit_on_error(with_retries(|| ledger_source.get_confirmed_slots(block, last)).await);
+            let mut line = format!("{} {} {} {}", block, last, confirmed_slots.len(), blocks.len());
+            for slot in confirmed_slots.iter().filter(|slot| !blocks.iter().any(|(fetched, _)| fetched == *slot)) {
+                line.push_str(format!(" {}", slot).as_str());
+            }
+            write_output(gaps_out, &line);
+        }
+
+        for (slot, block) in blocks {
+            if let Some(slots_out) = &mut slots_out {
+                write_output(slots_out, &slot_metadata(slot, &block, &vote_program_id));
+            }
+
+            for (_signatures, message, status) in block.transactions.into_iter().filter_map(|meta| match meta {
                 solana_transaction_status::TransactionWithStatusMeta::MissingMetadata(_) => {
                     // Can't use tx with missing metadata because can't know if it succeeded
                     eprintln!("Cannot use tx in slot {}", slot);
                     None
                 },
-                solana_transaction_status::TransactionWithStatusMeta::Complete(tx) => {
-                    if tx.meta.status.is_ok() {
-                        Some((tx.transaction.signatures, match tx.transaction.message {
-                            solana_sdk::message::VersionedMessage::Legacy(message) => message,
-                            solana_sdk::message::VersionedMessage::V0(message) => solana_sdk::message::Message {
-                                header : message.header,
-                                account_keys : message.account_keys,
-                                recent_blockhash : message.recent_blockhash,
-                                instructions : message.instructions
-                            }
-                        }))
-                    }
-                    // Ignore vote tx that failed, none of the slots landed
-                    else {
-                        None
-                    }
-                }
+                solana_transaction_status::TransactionWithStatusMeta::Complete(tx) => Some((
+                    tx.transaction.signatures,
+                    match tx.transaction.message {
+                        solana_sdk::message::VersionedMessage::Legacy(message) => message,
+                        // Instructions of a V0 message index into the static account keys followed by the
+                        // writable and then the readonly addresses loaded from address lookup tables
+                        solana_sdk::message::VersionedMessage::V0(message) => solana_sdk::message::Message {
+                            header : message.header,
+                            account_keys : message
+                                .account_keys
+                                .into_iter()
+                                .chain(tx.meta.loaded_addresses.writable)
+                                .chain(tx.meta.loaded_addresses.readonly)
+                                .collect(),
+                            recent_blockhash : message.recent_blockhash,
+                            instructions : message.instructions
+                        }
+                    },
+                    tx.meta.status
+                ))
             }) {
-                if let Some((_signatures, message)) = result {
-                    // Look for vote instructions
-                    for i in 0..message.instructions.len() {
-                        let instruction = &message.instructions[i];
-                        if message.account_keys[instruction.program_id_index as usize] == vote_program_id {
-                            // Now must parse the data as a vote instruction
-                            match solana_sdk::program_utils::limited_deserialize(&instruction.data) {
-                                Ok(VoteInstruction::Vote(vote)) | Ok(VoteInstruction::VoteSwitch(vote, _)) => {
-                                    // instruction account 0 is the voting vote account
-                                    let vote_account_pubkey = message.account_keys[instruction.accounts[0] as usize];
-                                    let mut to_insert = match vote_account_state.remove(&vote_account_pubkey) {
-                                        Some(existing) => existing,
-                                        None => 0_u64
-                                    };
-                                    // Write the slot and pubkey
-                                    print!("{} {}", slot, vote_account_pubkey);
+                // Look for vote instructions
+                for i in 0..message.instructions.len() {
+                    let instruction = &message.instructions[i];
+                    if message.account_keys.get(instruction.program_id_index as usize) == Some(&vote_program_id) {
+                        // Now must parse the data as a vote instruction
+                        if let Some(slots) = voted_slots(&instruction.data) {
+                            // instruction account 0 is the voting vote account
+                            let vote_account_pubkey = match instruction
+                                .accounts
+                                .first()
+                                .and_then(|index| message.account_keys.get(*index as usize))
+                            {
+                                Some(vote_account_pubkey) => *vote_account_pubkey,
+                                None => {
+                                    eprintln!("Cannot resolve vote account of vote tx in slot {}", slot);
+                                    continue;
+                                }
+                            };
+                            let vote_account = vote_account_pubkey.to_string();
+                            let line = match &status {
+                                Ok(()) => {
+                                    let mut to_insert =
+                                        vote_account_state.remove(&vote_account_pubkey).unwrap_or_default();
                                     // Write the voted on slots that are newer than to_insert and update to_insert
-                                    for voted_on_slot in vote.slots {
+                                    let mut voted_slots = vec![];
+                                    for voted_on_slot in slots {
                                         if voted_on_slot > to_insert {
-                                            print!(" {}", voted_on_slot);
+                                            voted_slots.push(voted_on_slot);
                                             to_insert = voted_on_slot;
                                         }
                                     }
-                                    vote_account_state.insert(vote_account_pubkey.clone(), to_insert);
-                                    println!("");
+                                    vote_account_state.insert(vote_account_pubkey, to_insert);
+                                    VoteLine::Landed { slot, vote_account, voted_slots }
                                 },
-                                _ => ()
-                            }
+                                // None of the slots of a failed vote tx landed, so just write why it failed
+                                Err(error) => VoteLine::Failed { slot, vote_account, error_kind : error_kind(error, i) }
+                            };
+                            out.write(&line, &vote_account_pubkey);
                         }
                     }
                 }
@@ -133,6 +508,224 @@ async fn main()
 
         block += limit;
 
-        eprintln!("There are {}/{} slots remaining", (args.last_slot - block), total);
+        batches += 1;
+
+        if let Some(checkpoint_path) = &checkpoint_path {
+            if batches.is_multiple_of(CHECKPOINT_BATCHES) || (block > args.last_slot) {
+                write_checkpoint(
+                    out.output(),
+                    slots_out.as_mut(),
+                    gaps_out.as_mut(),
+                    checkpoint_path,
+                    block,
+                    &vote_account_state
+                );
+            }
+        }
+
+        eprintln!("There are {}/{} slots remaining", (args.last_slot + 1) - block, total);
     }
+
+    exit_on_error(out.output().flush().map_err(|e| format!("Error writing output: {}", e)));
+
+    for extra_out in [&mut slots_out, &mut gaps_out].into_iter().flatten() {
+        exit_on_error(extra_out.flush().map_err(|e| format!("Error writing output: {}", e)));
+    }
+
+    if let Some(state_path) = &args.state_path {
+        exit_on_error(write_vote_account_state(state_path, &vote_account_state));
+    }
+}
+
+// Where vote data is written: as text lines, or as a binary vote stream
+enum VoteOutput
+{
+    Text(Output),
+
+    Binary(vote_stream::Writer<Output>)
+}
+
+impl VoteOutput
+{
+    fn write(
+        &mut self,
+        line : &VoteLine,
+        vote_account : &solana_sdk::pubkey::Pubkey
+    )
+    {
+        let result = match (self, line) {
+            (VoteOutput::Text(out), line) => writeln!(out, "{}", line),
+            (VoteOutput::Binary(writer), VoteLine::Landed { slot, voted_slots, .. }) => {
+                writer.write_landed(*slot, &vote_account.to_bytes(), voted_slots)
+            },
+            (VoteOutput::Binary(writer), VoteLine::Failed { slot, error_kind, .. }) => {
+                writer.write_failed(*slot, &vote_account.to_bytes(), error_kind)
+            },
+            // The header of a vote stream is written when it is opened
+            (VoteOutput::Binary(_), VoteLine::Header(_)) => Ok(())
+        };
+
+        exit_on_error(result.map_err(|e| format!("Error writing output: {}", e)));
+    }
+
+    fn output(&mut self) -> &mut Output
+    {
+        match self {
+            VoteOutput::Text(out) => out,
+            VoteOutput::Binary(writer) => writer.get_mut()
+        }
+    }
+}
+
+// Starts a binary vote stream in an opened output file.  If resuming, the vote accounts and error kinds that were
+// already given are read back from the file, which has been truncated to its length at the checkpoint.
+fn open_vote_stream(
+    out : Output,
+    path : &str,
+    header : &EpochHeader,
+    cluster : &str,
+    resume : bool
+) -> vote_stream::Writer<Output>
+{
+    if resume {
+        let existing =
+            exit_on_error(std::fs::File::open(path).map_err(|e| format!("Error opening output file {}: {}", path, e)));
+        return exit_on_error(
+            vote_stream::Writer::resume(out, std::io::BufReader::new(existing))
+                .map_err(|e| format!("Error resuming output file {}: {}", path, e))
+        );
+    }
+
+    let header = vote_stream::StreamHeader {
+        cluster : cluster.to_string(),
+        epoch : header.epoch,
+        first_slot : header.first_slot,
+        last_slot : header.last_slot,
+        tool_version : env!("CARGO_PKG_VERSION").to_string()
+    };
+
+    exit_on_error(vote_stream::Writer::new(out, &header).map_err(|e| format!("Error writing output: {}", e)))
+}
+
+fn write_output(
+    out : &mut Output,
+    line : &str
+)
+{
+    exit_on_error(writeln!(out, "{}", line).map_err(|e| format!("Error writing output: {}", e)));
+}
+
+// Opens a file for output.  If resuming from a checkpoint, the file is truncated to the length it had when the
+// checkpoint was taken and appended to; otherwise it is truncated completely.
+fn open_output(
+    path : &str,
+    resume_length : Option<u64>
+) -> Output
+{
+    let mut file = exit_on_error(
+        std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(resume_length.is_none())
+            .open(path)
+            .map_err(|e| format!("Error opening output file {}: {}", path, e))
+    );
+
+    if let Some(resume_length) = resume_length {
+        exit_on_error(
+            file.set_len(resume_length)
+                .and_then(|_| file.seek(std::io::SeekFrom::End(0)))
+                .map_err(|e| format!("Error truncating output file {}: {}", path, e))
+        );
+    }
+
+    Output::File(std::io::BufWriter::new(file))
+}
+
+// Durably writes everything written so far to an output file, and returns its length
+fn durable_length(out : &mut Output) -> u64
+{
+    match out {
+        Output::File(file) => exit_on_error(
+            file.flush()
+                .and_then(|_| file.get_ref().sync_data())
+                .and_then(|_| file.get_ref().metadata())
+                .map(|metadata| metadata.len())
+                .map_err(|e| format!("Error writing output: {}", e))
+        ),
+        Output::Stdout(_) => 0
+    }
+}
+
+// Records that everything before next_slot has been written to the output files, which must be durably written first
+fn write_checkpoint(
+    out : &mut Output,
+    slots_out : Option<&mut Output>,
+    gaps_out : Option<&mut Output>,
+    checkpoint_path : &str,
+    next_slot : u64,
+    vote_account_state : &state::VoteAccountState
+)
+{
+    let output_length = durable_length(out);
+
+    let slots_length = slots_out.map(durable_length).unwrap_or(0);
+
+    let gaps_length = gaps_out.map(durable_length).unwrap_or(0);
+
+    exit_on_error(
+        Checkpoint {
+            output_length,
+            slots_length,
+            gaps_length,
+            next_slot,
+            vote_account_state : vote_account_state.clone()
+        }
+        .write(checkpoint_path)
+    );
+}
+
+// Returns the slot metadata line for a block
+fn slot_metadata(
+    slot : u64,
+    block : &ConfirmedBlock,
+    vote_program_id : &solana_sdk::pubkey::Pubkey
+) -> String
+{
+    // The leader is the recipient of the block's fee reward
+    let leader = block
+        .rewards
+        .iter()
+        .find(|reward| reward.reward_type == Some(solana_sdk::reward_type::RewardType::Fee))
+        .map(|reward| reward.pubkey.clone());
+
+    // A vote transaction is one that includes a vote program instruction; program ids are always static account keys
+    let vote_transactions = block
+        .transactions
+        .iter()
+        .filter(|tx| {
+            let (account_keys, instructions) = match tx {
+                solana_transaction_status::TransactionWithStatusMeta::MissingMetadata(tx) => {
+                    (tx.message.account_keys.as_slice(), tx.message.instructions.as_slice())
+                },
+                solana_transaction_status::TransactionWithStatusMeta::Complete(tx) => {
+                    (tx.transaction.message.static_account_keys(), tx.transaction.message.instructions())
+                },
+            };
+            instructions
+                .iter()
+                .any(|instruction| account_keys.get(instruction.program_id_index as usize) == Some(vote_program_id))
+        })
+        .count();
+
+    format!(
+        "{} {} {} {} {} {} {}",
+        slot,
+        block.parent_slot,
+        block.block_height.map(|block_height| block_height.to_string()).unwrap_or("_".to_string()),
+        block.block_time.map(|block_time| block_time.to_string()).unwrap_or("_".to_string()),
+        leader.unwrap_or("_".to_string()),
+        vote_transactions,
+        block.transactions.len() - vote_transactions
+    )
 }
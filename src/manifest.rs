@@ -0,0 +1,422 @@
+// Manifests record what an output of the pipeline was made from: the programs that made it (by the hash of their
+// executables), its parameters, and the hash of each of its input files.  The manifest of an output is written beside
+// it, as a hidden file, once the output is complete.  Work whose manifest matches what it would now be made from, and
+// whose outputs all still exist, is up to date and can be skipped, so that adding one epoch to a study only costs one
+// epoch of work.  An input whose size and modification time haven't changed since its manifest was written is taken
+// to be unchanged without hashing it again.
+//
+// A manifest is a text file of lines of the form:
+// version VERSION
+// tool NAME HASH
+// parameter TEXT
+// input SIZE MODIFIED HASH PATH
+// output PATH
+
+use solana_sdk::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Default)]
+pub struct Manifest
+{
+    // (name, hash of executable)
+    tools : Vec<(String, String)>,
+
+    parameters : Vec<String>,
+
+    inputs : Vec<Input>,
+
+    outputs : Vec<String>
+}
+
+struct Input
+{
+    path : String,
+
+    size : u64,
+
+    // Nanoseconds since the Unix epoch
+    modified : u128,
+
+    // Computed only when needed
+    hash : Option<String>
+}
+
+// Hashes a program's executable, giving the (name, hash) of a tool of a manifest
+pub fn tool(path : &Path) -> Result<(String, String), String>
+{
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+    Ok((name, hash_file(path)?))
+}
+
+// The path of the manifest of an output: a hidden file beside it, e.g. data_processed/.600.manifest
+pub fn path_of(output : &Path) -> PathBuf
+{
+    let file_name = output.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+    output.with_file_name(format!(".{}.manifest", file_name))
+}
+
+impl Manifest
+{
+    pub fn new(tools : &[(String, String)]) -> Self
+    {
+        Manifest { tools : tools.to_vec(), ..Default::default() }
+    }
+
+    pub fn parameter(
+        &mut self,
+        parameter : &str
+    )
+    {
+        self.parameters.push(parameter.to_string());
+    }
+
+    pub fn input(
+        &mut self,
+        path : &Path
+    ) -> Result<(), String>
+    {
+        let (size, modified) = stat(path)?;
+
+        self.inputs.push(Input { path : path.to_string_lossy().to_string(), size, modified, hash : None });
+
+        Ok(())
+    }
+
+    pub fn output(
+        &mut self,
+        path : &Path
+    )
+    {
+        self.outputs.push(path.to_string_lossy().to_string());
+    }
+
+    // Whether the manifest at the given path is the same as this one, with all of its outputs present.  Inputs are
+    // hashed as needed to tell, and if an input turns out to be unchanged despite being modified, the manifest is
+    // rewritten with its new modification time, so that it isn't hashed again next time.
+    pub fn is_current(
+        &mut self,
+        path : &Path
+    ) -> Result<bool, String>
+    {
+        let previous = match std::fs::read_to_string(path) {
+            Ok(contents) => match Manifest::parse(&contents) {
+                Ok(previous) => previous,
+                // A manifest that can't be read describes nothing, so the work is just done again
+                Err(_) => return Ok(false)
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(format!("Error reading {}: {}", path.display(), e))
+        };
+
+        if (previous.tools != self.tools) ||
+            (previous.parameters != self.parameters) ||
+            (previous.outputs != self.outputs) ||
+            (previous.inputs.len() != self.inputs.len()) ||
+            self.outputs.iter().any(|output| !Path::new(output).exists())
+        {
+            return Ok(false);
+        }
+
+        let mut modified = false;
+
+        for (input, previous_input) in self.inputs.iter_mut().zip(&previous.inputs) {
+            if input.path != previous_input.path {
+                return Ok(false);
+            }
+            if (input.size == previous_input.size) && (input.modified == previous_input.modified) {
+                input.hash = previous_input.hash.clone();
+                continue;
+            }
+            let hash = hash_file(Path::new(&input.path))?;
+            if Some(&hash) != previous_input.hash.as_ref() {
+                return Ok(false);
+            }
+            input.hash = Some(hash);
+            modified = true;
+        }
+
+        if modified {
+            self.write(path)?;
+        }
+
+        Ok(true)
+    }
+
+    // Writes the manifest, hashing any inputs not yet hashed.  This must only be done once the outputs are complete.
+    pub fn write(
+        &mut self,
+        path : &Path
+    ) -> Result<(), String>
+    {
+        let mut contents = format!("version {}\n", env!("CARGO_PKG_VERSION"));
+
+        for (name, hash) in &self.tools {
+            contents.push_str(&format!("tool {} {}\n", name, hash));
+        }
+
+        for parameter in &self.parameters {
+            contents.push_str(&format!("parameter {}\n", parameter));
+        }
+
+        for input in &mut self.inputs {
+            let hash = match &input.hash {
+                Some(hash) => hash.clone(),
+                None => hash_file(Path::new(&input.path))?
+            };
+            contents.push_str(&format!("input {} {} {} {}\n", input.size, input.modified, hash, input.path));
+            input.hash = Some(hash);
+        }
+
+        for output in &self.outputs {
+            contents.push_str(&format!("output {}\n", output));
+        }
+
+        crate::runner::write_file(path, contents.as_bytes())
+    }
+
+    fn parse(contents : &str) -> Result<Self, String>
+    {
+        let mut manifest = Manifest::default();
+
+        for line in contents.lines() {
+            let (kind, rest) = line.split_once(' ').ok_or(format!("Invalid manifest line: {}", line))?;
+            match kind {
+                // For people reading the manifest; a build of another version has tools with other hashes anyway
+                "version" => (),
+                "tool" => {
+                    let (name, hash) = rest.split_once(' ').ok_or(format!("Invalid manifest line: {}", line))?;
+                    manifest.tools.push((name.to_string(), hash.to_string()));
+                },
+                "parameter" => manifest.parameters.push(rest.to_string()),
+                "input" => {
+                    let split : Vec<&str> = rest.splitn(4, ' ').collect();
+                    match split.as_slice() {
+                        [size, modified, hash, path] => manifest.inputs.push(Input {
+                            path : path.to_string(),
+                            size : size.parse().map_err(|_| format!("Invalid manifest line: {}", line))?,
+                            modified : modified.parse().map_err(|_| format!("Invalid manifest line: {}", line))?,
+                            hash : Some(hash.to_string())
+                        }),
+                        _ => return Err(format!("Invalid manifest line: {}", line))
+                    }
+                },
+                "output" => manifest.outputs.push(rest.to_string()),
+                _ => return Err(format!("Invalid manifest line: {}", line))
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+// Returns the size and modification time (in nanoseconds since the Unix epoch) of a file
+fn stat(path : &Path) -> Result<(u64, u128), String>
+{
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|modified| modified.as_nanos())
+        .unwrap_or(0);
+
+    Ok((metadata.len(), modified))
+}
+
+// SHA-256 of a file's contents
+fn hash_file(path : &Path) -> Result<String, String>
+{
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+
+    let mut hasher = Hasher::default();
+
+    let mut buffer = vec![0_u8; 1024 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.hash(&buffer[..read]);
+    }
+
+    Ok(hasher.result().to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // A fresh directory for a test, removed when dropped
+    struct TestDirectory(PathBuf);
+
+    impl TestDirectory
+    {
+        fn new(name : &str) -> Self
+        {
+            let path = std::env::temp_dir().join(format!("manifest_test_{}_{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TestDirectory(path)
+        }
+    }
+
+    impl Drop for TestDirectory
+    {
+        fn drop(&mut self)
+        {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn tools() -> Vec<(String, String)>
+    {
+        vec![("process_data".to_string(), "HASH".to_string())]
+    }
+
+    // The manifest of making output from input with the given parameter
+    fn manifest(
+        parameter : &str,
+        input : &Path,
+        output : &Path
+    ) -> Manifest
+    {
+        let mut manifest = Manifest::new(&tools());
+        manifest.parameter(parameter);
+        manifest.input(input).unwrap();
+        manifest.output(output);
+        manifest
+    }
+
+    fn set_modified(
+        path : &Path,
+        seconds : u64
+    )
+    {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds)).unwrap();
+    }
+
+    // A test directory holding an input, an output, and the manifest of making one from the other
+    fn made(name : &str) -> (TestDirectory, PathBuf, PathBuf, PathBuf)
+    {
+        let directory = TestDirectory::new(name);
+        let input = directory.0.join("600.gz");
+        let output = directory.0.join("600");
+        std::fs::write(&input, "votes").unwrap();
+        set_modified(&input, 1000);
+        std::fs::write(&output, "histograms").unwrap();
+        let path = path_of(&output);
+        manifest("0.99 landing", &input, &output).write(&path).unwrap();
+        (directory, input, output, path)
+    }
+
+    #[test]
+    fn is_current_when_nothing_changed()
+    {
+        let (_directory, input, output, path) = made("unchanged");
+
+        assert_eq!(path.file_name().unwrap(), ".600.manifest");
+
+        assert!(manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+    }
+
+    #[test]
+    fn is_not_current_without_manifest_or_output()
+    {
+        let (_directory, input, output, path) = made("missing");
+
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(!manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+
+        std::fs::write(&output, "histograms").unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+    }
+
+    #[test]
+    fn is_not_current_when_parameter_or_tool_changes()
+    {
+        let (_directory, input, output, path) = made("parameter");
+
+        assert!(!manifest("0.99 parent", &input, &output).is_current(&path).unwrap());
+
+        let mut rebuilt = Manifest::new(&[("process_data".to_string(), "OTHER".to_string())]);
+        rebuilt.parameter("0.99 landing");
+        rebuilt.input(&input).unwrap();
+        rebuilt.output(&output);
+
+        assert!(!rebuilt.is_current(&path).unwrap());
+    }
+
+    #[test]
+    fn is_not_current_when_input_changes()
+    {
+        let (_directory, input, output, path) = made("changed");
+
+        // Same size and modification time would be taken as unchanged, so the modification time moves too
+        std::fs::write(&input, "VOTES").unwrap();
+        set_modified(&input, 2000);
+
+        assert!(!manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+    }
+
+    #[test]
+    fn touched_input_is_hashed_once_and_manifest_rewritten()
+    {
+        let (_directory, input, output, path) = made("touched");
+
+        let hash = hash_file(&input).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+
+        assert!(contents.contains(&format!("input 5 1000000000000 {} ", hash)), "{}", contents);
+
+        set_modified(&input, 2000);
+
+        assert!(manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+
+        // The manifest now has the new modification time, with the same hash, so the input is not hashed again
+        let contents = std::fs::read_to_string(&path).unwrap();
+
+        assert!(contents.contains(&format!("input 5 2000000000000 {} ", hash)), "{}", contents);
+
+        // Which is trusted from now on without hashing: even a change of content that keeps the same size and
+        // modification time goes unnoticed
+        std::fs::write(&input, "VOTES").unwrap();
+        set_modified(&input, 2000);
+
+        assert!(manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+    }
+
+    #[test]
+    fn parse_reads_what_write_writes()
+    {
+        let (_directory, input, output, path) = made("parse");
+
+        let parsed = Manifest::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(parsed.tools, tools());
+        assert_eq!(parsed.parameters, vec!["0.99 landing".to_string()]);
+        assert_eq!(parsed.inputs.len(), 1);
+        assert_eq!(parsed.inputs[0].path, input.to_string_lossy());
+        assert_eq!((parsed.inputs[0].size, parsed.inputs[0].modified), (5, 1000000000000));
+        assert_eq!(parsed.outputs, vec![output.to_string_lossy().to_string()]);
+
+        assert!(Manifest::parse("input 5 x HASH path").is_err());
+        assert!(Manifest::parse("unknown line").is_err());
+
+        // An unreadable manifest describes nothing, so the work is redone
+        std::fs::write(&path, "garbage").unwrap();
+
+        assert!(!manifest("0.99 landing", &input, &output).is_current(&path).unwrap());
+    }
+}
@@ -0,0 +1,145 @@
+// Model of stakers moving their stake between validators in search of yield.  Each epoch, the yield of every validator
+// is compared to the stake weighted mean yield.  A fraction (the mobility) of the stake of validators whose yield is
+// more than a margin (the stickiness, as a fraction of the mean yield) below the mean leaves them, and is delegated
+// instead to the validators whose yield is more than that margin above the mean, in proportion to their stake times
+// how far above the mean their yield is.
+
+pub struct MigrationModel
+{
+    // Fraction of the stake of a low yield validator that leaves it each epoch
+    pub mobility : f64,
+
+    // How far below the mean yield, as a fraction of it, a validator's yield must be before stake leaves it, and how
+    // far above it a validator's yield must be for stake to move to it
+    pub stickiness : f64
+}
+
+impl MigrationModel
+{
+    pub fn new(
+        mobility : f64,
+        stickiness : f64
+    ) -> Result<Self, String>
+    {
+        if !(0_f64..=1_f64).contains(&mobility) {
+            return Err(format!("Invalid mobility {}: must be from 0 to 1", mobility));
+        }
+
+        if stickiness < 0_f64 {
+            return Err(format!("Invalid stickiness {}: must not be negative", stickiness));
+        }
+
+        Ok(MigrationModel { mobility, stickiness })
+    }
+
+    // Moves stake between validators for one epoch, given the yield of each validator.  The total stake is unchanged.
+    pub fn step(
+        &self,
+        stakes : &mut [f64],
+        yields : &[f64]
+    )
+    {
+        let total_stake = stakes.iter().sum::<f64>();
+
+        if total_stake == 0_f64 {
+            return;
+        }
+
+        let mean_yield = stakes.iter().zip(yields).map(|(stake, y)| stake * y).sum::<f64>() / total_stake;
+
+        let margin = mean_yield * self.stickiness;
+
+        // Weight of each validator that stake moves to
+        let attraction : Vec<f64> = stakes
+            .iter()
+            .zip(yields)
+            .map(|(stake, y)| {
+                if *y > (mean_yield + margin) {
+                    stake * (y - mean_yield)
+                }
+                else {
+                    0_f64
+                }
+            })
+            .collect();
+
+        let total_attraction = attraction.iter().sum::<f64>();
+
+        // With nowhere better to go, stake stays put
+        if total_attraction == 0_f64 {
+            return;
+        }
+
+        let mut moved = 0_f64;
+
+        for (stake, y) in stakes.iter_mut().zip(yields) {
+            if *y < (mean_yield - margin) {
+                let leaving = *stake * self.mobility;
+                *stake -= leaving;
+                moved += leaving;
+            }
+        }
+
+        for (stake, attraction) in stakes.iter_mut().zip(attraction) {
+            *stake += moved * (attraction / total_attraction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn stake_leaves_low_yield_validators_for_high_yield_ones()
+    {
+        let model = MigrationModel::new(0.5, 0_f64).unwrap();
+
+        // Mean yield 2: half of the first validator's stake moves to the third
+        let mut stakes = vec![100_f64, 100_f64, 100_f64];
+        model.step(&mut stakes, &[1_f64, 2_f64, 3_f64]);
+        assert_eq!(stakes, vec![50_f64, 100_f64, 150_f64]);
+
+        // Mean yield 2: the 100 that leaves the first two is split 1:3 by stake times yield above the mean
+        let mut stakes = vec![100_f64, 100_f64, 100_f64, 100_f64];
+        model.step(&mut stakes, &[0_f64, 0_f64, 3_f64, 5_f64]);
+        assert_eq!(stakes, vec![50_f64, 50_f64, 125_f64, 175_f64]);
+    }
+
+    #[test]
+    fn stickiness_keeps_stake_near_the_mean_in_place()
+    {
+        // A margin of 0.5 either side of the mean yield of 2 still moves stake
+        let mut stakes = vec![100_f64, 100_f64, 100_f64];
+        MigrationModel::new(0.5, 0.25).unwrap().step(&mut stakes, &[1_f64, 2_f64, 3_f64]);
+        assert_eq!(stakes, vec![50_f64, 100_f64, 150_f64]);
+
+        // A margin of 1.2 does not
+        let mut stakes = vec![100_f64, 100_f64, 100_f64];
+        MigrationModel::new(0.5, 0.6).unwrap().step(&mut stakes, &[1_f64, 2_f64, 3_f64]);
+        assert_eq!(stakes, vec![100_f64, 100_f64, 100_f64]);
+    }
+
+    #[test]
+    fn stake_stays_put_with_nowhere_better_to_go()
+    {
+        let model = MigrationModel::new(1_f64, 0_f64).unwrap();
+
+        let mut stakes = vec![100_f64, 300_f64];
+        model.step(&mut stakes, &[2_f64, 2_f64]);
+        assert_eq!(stakes, vec![100_f64, 300_f64]);
+
+        let mut stakes = vec![0_f64, 0_f64];
+        model.step(&mut stakes, &[1_f64, 2_f64]);
+        assert_eq!(stakes, vec![0_f64, 0_f64]);
+    }
+
+    #[test]
+    fn invalid_models_are_rejected()
+    {
+        assert!(MigrationModel::new(-0.1, 0_f64).is_err());
+        assert!(MigrationModel::new(1.5, 0_f64).is_err());
+        assert!(MigrationModel::new(0.5, -1_f64).is_err());
+    }
+}
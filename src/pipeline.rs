@@ -0,0 +1,832 @@
+// Runs the stages of the pipeline over every epoch, in place of the scripts that used to: fetch (fetch_data), process
+// (process_data), calculate (calculate_results), and collate (collate_results), or all of them in turn with run, and
+// sweep (calculate_results) to compare many parameterizations of the linear credit policy over all epochs.  The
+// files of each stage are kept in the directories of layout.rs, relative to the directory this is run in.  Epochs are
+// processed, calculated, and collated in parallel, on at most --jobs worker threads, with a line reported as each
+// finishes and a summary of every failure at the end.  A failed epoch leaves no output behind, and does not stop the
+// other epochs; this exits with -1 if anything failed.  Each output is written with a manifest of what it was made
+// from (see manifest.rs), and work whose manifest is unchanged is skipped, unless --force is given.
+
+use fetch_data::layout;
+use fetch_data::manifest::{self, Manifest};
+use fetch_data::runner::{self, Job};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// The validators.app details giving the data centers, names, and icons of validators, used if present (see
+// fetch_validators_app_mainnet_beta.sh)
+const VALIDATORS_PATH : &str = "validators_app_mainnet_beta.json";
+
+// Copied into each directory of HTML pages, to make their tables sortable
+const SORTTABLE_PATH : &str = "sorttable.js";
+
+const USAGE : &str = "Usage: pipeline [--jobs N] [--force] fetch <ledger_source> <first_epoch> <last_epoch> \
+                      [<epoch_schedule>]
+       pipeline [--jobs N] [--force] process [<min_coverage> [<latency_origin> [<credit_mode> [<block_slots>]]]]
+       pipeline [--jobs N] [--force] calculate <credit_policy> [theoretical]
+       pipeline [--jobs N] [--force] calculate <grace_period> <max_credits> <multiplier> [theoretical]
+       pipeline [--jobs N] [--force] collate
+       pipeline [--jobs N] [--force] sweep <graces> <max_credits> <multipliers>
+       pipeline [--jobs N] [--force] run <ledger_source> <first_epoch> <last_epoch> <epoch_schedule|_> \
+                      <credit_policy>...";
+
+// (description, error) of each job that failed
+type Failures = Vec<(String, String)>;
+
+#[derive(Clone, Copy)]
+struct Options
+{
+    // Number of jobs to run at once
+    workers : usize,
+
+    // Whether to redo work that is up to date
+    force : bool
+}
+
+// The jobs of a stage, leaving out those whose outputs are up to date
+struct Plan
+{
+    options : Options,
+
+    // (name, hash) of the programs that the stage's outputs are made by
+    tools : Vec<(String, String)>,
+
+    jobs : Vec<Job>,
+
+    up_to_date : usize
+}
+
+fn main()
+{
+    let mut args : Vec<String> = std::env::args().skip(1).collect();
+
+    let mut options = Options {
+        workers : std::thread::available_parallelism().map(|workers| workers.get()).unwrap_or(1),
+        force : false
+    };
+
+    // Optionally first "--jobs" and the number of epochs to work on at once, defaulting to the number of CPUs, and
+    // "--force" to redo work even if it is up to date
+    loop {
+        match args.first().map(String::as_str) {
+            Some("--jobs") => {
+                let workers = args.get(1).and_then(|arg| arg.parse::<usize>().ok()).filter(|workers| *workers > 0);
+                options.workers = workers.unwrap_or_else(|| {
+                    eprintln!("--jobs must be followed by a number of jobs of at least 1");
+                    std::process::exit(-1);
+                });
+                args.drain(0..2);
+            },
+            Some("--force") => {
+                options.force = true;
+                args.remove(0);
+            },
+            _ => break
+        }
+    }
+
+    let result = match args.first().map(String::as_str) {
+        Some("fetch") => fetch(&args[1..]),
+        Some("process") => process(&args[1..], options),
+        Some("calculate") => calculate(&args[1..], options),
+        Some("collate") if args.len() == 1 => collate(options),
+        Some("sweep") => sweep(&args[1..], options),
+        Some("run") => run(&args[1..], options),
+        _ => Err(USAGE.to_string())
+    };
+
+    let failures = result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    });
+
+    if !failures.is_empty() {
+        eprintln!("{} failed:", failures.len());
+        for (description, e) in &failures {
+            eprintln!("  {}: {}", description, e);
+        }
+        std::process::exit(-1);
+    }
+}
+
+impl Plan
+{
+    // A plan for a stage whose outputs are made by this program and the given one
+    fn new(
+        options : Options,
+        program : &Path
+    ) -> Result<Self, String>
+    {
+        let this_program = std::env::current_exe().map_err(|e| format!("Error finding this program: {}", e))?;
+
+        let tools = vec![manifest::tool(&this_program)?, manifest::tool(program)?];
+
+        Ok(Plan { options, tools, jobs : vec![], up_to_date : 0 })
+    }
+
+    // A manifest to describe a job's outputs, made by the stage's tools
+    fn manifest(&self) -> Manifest
+    {
+        Manifest::new(&self.tools)
+    }
+
+    // Adds a job that makes the outputs described by the manifest, unless they are up to date.  The manifest is
+    // written once the job has succeeded, beside the first of its outputs.
+    fn add(
+        &mut self,
+        description : String,
+        mut manifest : Manifest,
+        output : &Path,
+        work : impl FnOnce() -> Result<(), String> + Send + 'static
+    ) -> Result<(), String>
+    {
+        let manifest_path = manifest::path_of(output);
+
+        if self.is_current(&mut manifest, output)? {
+            self.up_to_date += 1;
+            return Ok(());
+        }
+
+        self.jobs.push(Job {
+            description,
+            work : Box::new(move || {
+                // Outputs that fail part way through must not be taken for up to date by the manifest of an earlier run
+                let _ = std::fs::remove_file(&manifest_path);
+                work()?;
+                manifest.write(&manifest_path)
+            })
+        });
+
+        Ok(())
+    }
+
+    // Whether the outputs described by the manifest, the first of which is given, are up to date, which with --force
+    // they never are
+    fn is_current(
+        &self,
+        manifest : &mut Manifest,
+        output : &Path
+    ) -> Result<bool, String>
+    {
+        Ok(!self.options.force && manifest.is_current(&manifest::path_of(output))?)
+    }
+
+    fn run(
+        self,
+        stage : &str
+    ) -> Failures
+    {
+        if self.up_to_date > 0 {
+            eprintln!("{}: {} up to date, {} to do (--force to redo all)", stage, self.up_to_date, self.jobs.len());
+        }
+
+        runner::run_jobs(self.jobs, self.options.workers)
+    }
+}
+
+// Fetches the given range of epochs, in order, each one starting from the vote account state saved at the end of the
+// previous epoch.  Epochs that were already fetched are skipped, and an epoch that was interrupted part way through is
+// resumed from its checkpoint.  fetch_data reports its own progress.  Fetching stops at the first epoch that fails,
+// since the epochs after it would start from the wrong state.
+fn fetch(args : &[String]) -> Result<Failures, String>
+{
+    let (ledger_source, first_epoch, last_epoch, epoch_schedule) = match args {
+        [ledger_source, first_epoch, last_epoch] => (ledger_source, first_epoch, last_epoch, "mainnet"),
+        [ledger_source, first_epoch, last_epoch, epoch_schedule] => {
+            (ledger_source, first_epoch, last_epoch, epoch_schedule.as_str())
+        },
+        _ => return Err(USAGE.to_string())
+    };
+
+    let first_epoch = first_epoch.parse::<u64>().map_err(|e| format!("Invalid first epoch {}: {}", first_epoch, e))?;
+
+    let last_epoch = last_epoch.parse::<u64>().map_err(|e| format!("Invalid last epoch {}: {}", last_epoch, e))?;
+
+    for directory in [layout::DATA, layout::SLOTS, layout::GAPS] {
+        create_directory(Path::new(directory))?;
+    }
+
+    let program = runner::program_path("fetch_data")?;
+
+    for epoch in first_epoch..=last_epoch {
+        let data_path = epoch_path(layout::DATA, epoch, ".gz");
+        if data_path.exists() {
+            continue;
+        }
+
+        eprintln!("fetch epoch {}", epoch);
+
+        if let Err(e) = fetch_epoch(&program, ledger_source, epoch_schedule, epoch) {
+            return Ok(vec![(format!("fetch epoch {}", epoch), e)]);
+        }
+    }
+
+    Ok(vec![])
+}
+
+fn fetch_epoch(
+    program : &Path,
+    ledger_source : &str,
+    epoch_schedule : &str,
+    epoch : u64
+) -> Result<(), String>
+{
+    let state_path = epoch_path(layout::DATA, epoch, ".state");
+
+    let output_path = epoch_path(layout::DATA, epoch, ".txt");
+
+    let slots_path = epoch_path(layout::SLOTS, epoch, ".txt");
+
+    // A checkpoint holds its own state, so any state left from an earlier attempt is replaced by the previous epoch's
+    let _ = std::fs::remove_file(&state_path);
+
+    if let Some(previous_epoch) = epoch.checked_sub(1) {
+        let previous_state_path = epoch_path(layout::DATA, previous_epoch, ".state");
+        if previous_state_path.exists() {
+            std::fs::copy(&previous_state_path, &state_path)
+                .map_err(|e| format!("Error copying {}: {}", previous_state_path.display(), e))?;
+        }
+    }
+
+    // fetch_data runs for hours, so its progress goes straight to the terminal rather than being captured
+    let status = std::process::Command::new(program)
+        .arg(ledger_source)
+        .arg(epoch_schedule)
+        .arg(epoch.to_string())
+        .arg(&state_path)
+        .arg(&output_path)
+        .arg(&slots_path)
+        .arg(epoch_path(layout::GAPS, epoch, ""))
+        .status()
+        .map_err(|e| format!("Error running {}: {}", program.display(), e))?;
+
+    if !status.success() {
+        return Err(format!("{} failed ({})", program.display(), status));
+    }
+
+    // The slot metadata is compressed first, so that the vote data's .gz only exists once the epoch is complete
+    gzip(&slots_path, &epoch_path(layout::SLOTS, epoch, ".gz"))?;
+
+    gzip(&output_path, &epoch_path(layout::DATA, epoch, ".gz"))?;
+
+    let mut checkpoint_path = output_path.into_os_string();
+
+    checkpoint_path.push(".checkpoint");
+
+    let _ = std::fs::remove_file(checkpoint_path);
+
+    Ok(())
+}
+
+// Compresses a file into another, removing the original once the compressed file is complete.  Fetched epochs are
+// gigabytes, so this streams into a temporary file that is renamed into place.
+fn gzip(
+    path : &Path,
+    gz_path : &Path
+) -> Result<(), String>
+{
+    let mut temporary_path = gz_path.as_os_str().to_owned();
+
+    temporary_path.push(".tmp");
+
+    let result = std::fs::File::open(path)
+        .and_then(|mut input| {
+            let mut encoder = flate2::write::GzEncoder::new(
+                std::io::BufWriter::new(std::fs::File::create(&temporary_path)?),
+                flate2::Compression::default()
+            );
+            std::io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?.flush()
+        })
+        .and_then(|_| std::fs::rename(&temporary_path, gz_path));
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temporary_path);
+        return Err(format!("Error compressing {}: {}", path.display(), e));
+    }
+
+    std::fs::remove_file(path).map_err(|e| format!("Error removing {}: {}", path.display(), e))
+}
+
+// Processes every fetched epoch, using whatever metadata (gaps, slots, stakes) the epoch has.  Arguments are optional
+// minimum coverage (default 0.99), latency origin (default "landing"), credit mode (default "landed"), and block slots
+// (default none), as process_data takes them.  The epochs that are not up to date are all processed by one run of
+// process_data epochs, on at most --jobs worker threads, which reports each epoch as it finishes.  In tower credit
+// mode each epoch starts from the towers of the previous one, so every epoch after one that is not up to date is
+// processed again too.
+fn process(
+    args : &[String],
+    options : Options
+) -> Result<Failures, String>
+{
+    if args.len() > 4 {
+        return Err(USAGE.to_string());
+    }
+
+    let min_coverage = args.first().cloned().unwrap_or("0.99".to_string());
+
+    let latency_origin = args.get(1).cloned().unwrap_or("landing".to_string());
+
+    let credit_mode = args.get(2).cloned().unwrap_or("landed".to_string());
+
+    let towers = credit_mode == "tower";
+
+    let block_slots = args.get(3).cloned();
+
+    for directory in [layout::PROCESSED, layout::FAILURES] {
+        create_directory(Path::new(directory))?;
+    }
+
+    if block_slots.is_some() {
+        create_directory(Path::new(layout::BLOCKS))?;
+    }
+
+    if towers {
+        create_directory(Path::new(layout::TOWERS))?;
+    }
+
+    let program = runner::program_path("process_data")?;
+
+    let plan = Plan::new(options, &program)?;
+
+    let mut up_to_date = 0;
+
+    let validators = if Path::new(VALIDATORS_PATH).is_file() { VALIDATORS_PATH } else { "_" };
+
+    // The arguments of process_data epochs, to which the vote data of each epoch to process is added
+    let mut epochs_args = vec![
+        "epochs".to_string(),
+        options.workers.to_string(),
+        validators.to_string(),
+        min_coverage,
+        latency_origin,
+        credit_mode,
+        block_slots.clone().unwrap_or("_".to_string()),
+    ];
+
+    // Every epoch is processed with the same parameters, whatever the number of workers
+    let parameters = epochs_args[2..].join(" ");
+
+    // The manifest of an epoch as its inputs stand now, which for the previous epoch's towers is only once that epoch
+    // has been processed
+    let epoch_manifest = |epoch : u64, data_path : &Path| -> Result<Manifest, String> {
+        let mut manifest = plan.manifest();
+        manifest.parameter(&parameters);
+        manifest.input(data_path)?;
+        // The validators.app file and the epoch's gaps, slots, stakes, and previous towers, which process_data uses if
+        // they exist
+        let inputs = [
+            validators.to_string(),
+            existing_path_arg(epoch_path(layout::GAPS, epoch, "")),
+            existing_path_arg(epoch_path(layout::SLOTS, epoch, ".gz")),
+            existing_path_arg(epoch_path(layout::STAKES, epoch, "")),
+            match epoch.checked_sub(1) {
+                Some(previous_epoch) if towers => existing_path_arg(epoch_path(layout::TOWERS, previous_epoch, "")),
+                _ => "_".to_string()
+            }
+        ];
+        for input in inputs.iter().filter(|input| *input != "_") {
+            manifest.input(Path::new(input))?;
+        }
+        manifest.output(&epoch_path(layout::PROCESSED, epoch, ""));
+        manifest.output(&epoch_path(layout::FAILURES, epoch, ""));
+        if block_slots.is_some() {
+            manifest.output(&epoch_path(layout::BLOCKS, epoch, ""));
+        }
+        if towers {
+            manifest.output(&epoch_path(layout::TOWERS, epoch, ""));
+        }
+        Ok(manifest)
+    };
+
+    // (epoch, data path, output path) of each epoch to process
+    let mut to_do = vec![];
+
+    // Epochs converted to vote streams (see convert_data.sh) are read from those, which is faster
+    let mut epochs = layout::list_epochs(Path::new(layout::DATA), ".gz")?;
+
+    epochs.extend(layout::list_epochs(Path::new(layout::DATA), layout::VOTE_STREAM_SUFFIX)?);
+
+    epochs.sort();
+
+    epochs.dedup();
+
+    for epoch in epochs {
+        let vote_stream_path = epoch_path(layout::DATA, epoch, layout::VOTE_STREAM_SUFFIX);
+        let data_path =
+            if vote_stream_path.exists() { vote_stream_path } else { epoch_path(layout::DATA, epoch, ".gz") };
+        let output_path = epoch_path(layout::PROCESSED, epoch, "");
+
+        // In tower credit mode, the towers an epoch starts from change whenever an earlier epoch is processed again
+        let follows_redone_epoch = towers && !to_do.is_empty();
+
+        if !follows_redone_epoch && plan.is_current(&mut epoch_manifest(epoch, &data_path)?, &output_path)? {
+            up_to_date += 1;
+            continue;
+        }
+
+        // Output of an earlier run would otherwise be taken for this one's, should process_data not get to the epoch
+        let _ = std::fs::remove_file(manifest::path_of(&output_path));
+        let _ = std::fs::remove_file(&output_path);
+
+        epochs_args.push(path_arg(&data_path));
+        to_do.push((epoch, data_path, output_path));
+    }
+
+    if up_to_date > 0 {
+        eprintln!("process: {} up to date, {} to do (--force to redo all)", up_to_date, to_do.len());
+    }
+
+    if to_do.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // process_data reports each epoch as it finishes, and every failure again at the end, straight to the terminal
+    let status = std::process::Command::new(&program)
+        .args(&epochs_args)
+        .status()
+        .map_err(|e| format!("Error running {}: {}", program.display(), e))?;
+
+    // process_data removes the output of every epoch that fails, so each epoch with output succeeded
+    let mut failures = vec![];
+
+    for (epoch, data_path, output_path) in to_do {
+        let result = if output_path.exists() {
+            epoch_manifest(epoch, &data_path).and_then(|mut manifest| manifest.write(&manifest::path_of(&output_path)))
+        }
+        else {
+            Err(format!("not processed ({} failed, see above)", program.display()))
+        };
+        if let Err(e) = result {
+            failures.push((format!("process epoch {}", epoch), e));
+        }
+    }
+
+    if failures.is_empty() && !status.success() {
+        failures.push(("process".to_string(), format!("{} failed ({})", program.display(), status)));
+    }
+
+    Ok(failures)
+}
+
+// Calculates the results of every processed epoch under a credit policy, given as calculate_results takes it, into a
+// directory of results named after the policy.  Optionally followed by "theoretical", to give pcts relative to the
+// maximum credits achievable in each epoch, which requires the epoch's slot metadata.
+fn calculate(
+    args : &[String],
+    options : Options
+) -> Result<Failures, String>
+{
+    let (policy, theoretical) = match args.split_last() {
+        Some((last, policy)) if last == "theoretical" => (policy, true),
+        _ => (args, false)
+    };
+
+    let directory = match policy {
+        // The policy names the results directory, with its separators (and any table path) turned into underscores
+        [spec] => {
+            fetch_data::credit_policy::parse(spec)?;
+            spec.replace([':', ',', '=', '/'], "_")
+        },
+        [grace_period, max_credits, multiplier] => {
+            fetch_data::credit_policy::parse(&format!("linear:{}:{}:{}", grace_period, max_credits, multiplier))?;
+            format!("{}_{}_{}", grace_period, max_credits, multiplier)
+        },
+        _ => return Err(USAGE.to_string())
+    };
+
+    let results_directory = Path::new(layout::RESULTS).join(directory);
+
+    create_directory(&results_directory)?;
+
+    let program = runner::program_path("calculate_results")?;
+
+    let mut plan = Plan::new(options, &program)?;
+
+    for epoch in layout::list_epochs(Path::new(layout::PROCESSED), "")? {
+        let processed_path = epoch_path(layout::PROCESSED, epoch, "");
+        let slots_path = epoch_path(layout::SLOTS, epoch, ".gz");
+
+        let normalization = if theoretical { vec!["theoretical".to_string(), path_arg(&slots_path)] } else { vec![] };
+
+        // (results file prefix, level, options); epochs processed with stakes also get stake weighted data center
+        // results and the stake weighted metrics of the whole cluster
+        let mut levels = vec![
+            ("v", "v", normalization.clone()),
+            ("d", "d", normalization.clone()),
+            ("c", "v", vec!["concentration".to_string()]),
+        ];
+
+        if epoch_path(layout::STAKES, epoch, "").exists() {
+            levels.push(("s", "s", normalization));
+            levels.push(("m", "v", vec!["cluster".to_string()]));
+        }
+
+        // (output path, arguments)
+        let outputs : Vec<(PathBuf, Vec<String>)> = levels
+            .into_iter()
+            .map(|(prefix, level, options)| {
+                (
+                    results_directory.join(format!("{}_{}", prefix, epoch)),
+                    std::iter::once(level.to_string()).chain(policy.iter().cloned()).chain(options).collect()
+                )
+            })
+            .collect();
+
+        let mut manifest = plan.manifest();
+        manifest.input(&processed_path)?;
+        // Missing slot metadata is reported by calculate_results
+        if theoretical && slots_path.exists() {
+            manifest.input(&slots_path)?;
+        }
+        for (output_path, args) in &outputs {
+            manifest.parameter(&args.join(" "));
+            manifest.output(output_path);
+        }
+
+        let program = program.clone();
+        plan.add(format!("calculate epoch {}", epoch), manifest, &outputs[0].0.clone(), move || {
+            calculate_epoch(&program, &processed_path, &outputs)
+        })?;
+    }
+
+    Ok(plan.run("calculate"))
+}
+
+// Writes each output by running calculate_results on the processed epoch with the output's arguments
+fn calculate_epoch(
+    program : &Path,
+    processed_path : &Path,
+    outputs : &[(PathBuf, Vec<String>)]
+) -> Result<(), String>
+{
+    for (output_path, args) in outputs {
+        let result = std::fs::File::open(processed_path)
+            .map_err(|e| format!("Error opening {}: {}", processed_path.display(), e))
+            .and_then(|processed| runner::run(program, args, Some(Box::new(processed))))
+            .and_then(|results| runner::write_file(output_path, results.as_bytes()));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(output_path);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+// Sweeps the linear credit policy over every combination of the given graces, max credits, and multipliers, each a
+// list of values and ranges as calculate_results takes them, writing one table per level into the sweeps directory
+// (v and d) that covers all of the processed epochs together
+fn sweep(
+    args : &[String],
+    options : Options
+) -> Result<Failures, String>
+{
+    if args.len() != 3 {
+        return Err(USAGE.to_string());
+    }
+
+    let epochs = layout::list_epochs(Path::new(layout::PROCESSED), "")?;
+
+    if epochs.is_empty() {
+        return Err(format!("No processed epochs in {}", layout::PROCESSED));
+    }
+
+    create_directory(Path::new(layout::SWEEPS))?;
+
+    let program = runner::program_path("calculate_results")?;
+
+    let mut plan = Plan::new(options, &program)?;
+
+    let processed_paths : Vec<PathBuf> = epochs.iter().map(|epoch| epoch_path(layout::PROCESSED, *epoch, "")).collect();
+
+    for level in ["v", "d"] {
+        let output_path = Path::new(layout::SWEEPS).join(level);
+        let args : Vec<String> = [level, "sweep"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(args.iter().cloned())
+            .chain(processed_paths.iter().map(|path| path_arg(path)))
+            .collect();
+
+        let mut manifest = plan.manifest();
+        manifest.parameter(&args.join(" "));
+        for processed_path in &processed_paths {
+            manifest.input(processed_path)?;
+        }
+        manifest.output(&output_path);
+
+        let program = program.clone();
+        plan.add(format!("sweep {}", output_path.display()), manifest, &output_path.clone(), move || {
+            let result =
+                runner::run(&program, &args, None).and_then(|sweep| runner::write_file(&output_path, sweep.as_bytes()));
+            if result.is_err() {
+                let _ = std::fs::remove_file(&output_path);
+            }
+            result
+        })?;
+    }
+
+    Ok(plan.run("sweep"))
+}
+
+// Writes the HTML pages of every directory of results: one per epoch and level, and one per level averaging all of
+// the epochs that have results
+fn collate(options : Options) -> Result<Failures, String>
+{
+    let program = runner::program_path("collate_results")?;
+
+    let mut plan = Plan::new(options, &program)?;
+
+    let mut directories = vec![];
+
+    if Path::new(layout::RESULTS).exists() {
+        for entry in
+            std::fs::read_dir(layout::RESULTS).map_err(|e| format!("Error reading {}: {}", layout::RESULTS, e))?
+        {
+            let entry = entry.map_err(|e| format!("Error reading {}: {}", layout::RESULTS, e))?;
+            if entry.path().is_dir() {
+                directories.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+    }
+
+    directories.sort();
+
+    let epochs = layout::list_epochs(Path::new(layout::PROCESSED), "")?;
+
+    // Each directory of HTML pages is created with its copy of sorttable.js before any page is written into it
+    let mut html_directories = std::collections::BTreeSet::new();
+
+    // (level, results paths, title, html path) of each page
+    let mut pages = vec![];
+
+    for directory in directories {
+        let results_directory = Path::new(layout::RESULTS).join(&directory);
+        let params = directory.replace('_', " ");
+        // (level, results file prefix, title), where stake weighted data centers are shown as data centers are
+        for (level, prefix, what) in
+            [("v", "v", "Validators"), ("d", "d", "Data Centers"), ("d", "s", "Stake Weighted Data Centers")]
+        {
+            let epochs : Vec<u64> = epochs
+                .iter()
+                .cloned()
+                .filter(|epoch| results_directory.join(format!("{}_{}", prefix, epoch)).exists())
+                .collect();
+            for epoch in &epochs {
+                let html_directory = Path::new(layout::HTML).join(epoch.to_string());
+                html_directories.insert(html_directory.clone());
+                pages.push((
+                    level,
+                    vec![results_directory.join(format!("{}_{}", prefix, epoch))],
+                    format!("Epoch {} Params {} {}", epoch, params, what),
+                    html_directory.join(format!("{}_{}.html", prefix, directory))
+                ));
+            }
+            if !epochs.is_empty() {
+                let html_directory = Path::new(layout::HTML).join("avg");
+                html_directories.insert(html_directory.clone());
+                pages.push((
+                    level,
+                    epochs.iter().map(|epoch| results_directory.join(format!("{}_{}", prefix, epoch))).collect(),
+                    format!(
+                        "Average for epochs ({}) Params {} {}",
+                        epochs.iter().map(u64::to_string).collect::<Vec<String>>().join(" "),
+                        params,
+                        what
+                    ),
+                    html_directory.join(format!("{}_{}.html", prefix, directory))
+                ));
+            }
+        }
+    }
+
+    if !html_directories.is_empty() {
+        let sorttable =
+            std::fs::read(SORTTABLE_PATH).map_err(|e| format!("Error reading {}: {}", SORTTABLE_PATH, e))?;
+        for html_directory in &html_directories {
+            create_directory(html_directory)?;
+            runner::write_file(&html_directory.join(SORTTABLE_PATH), &sorttable)?;
+        }
+    }
+
+    for (level, results_paths, title, html_path) in pages {
+        add_collate_job(&mut plan, &program, level, results_paths, title, html_path)?;
+    }
+
+    Ok(plan.run("collate"))
+}
+
+// Adds a job writing one HTML page of the results in the given files, which are averaged if more than one
+fn add_collate_job(
+    plan : &mut Plan,
+    program : &Path,
+    level : &str,
+    results_paths : Vec<PathBuf>,
+    title : String,
+    html_path : PathBuf
+) -> Result<(), String>
+{
+    let program = program.to_path_buf();
+
+    let mut args = vec![level.to_string()];
+
+    if Path::new(VALIDATORS_PATH).is_file() {
+        args.push(VALIDATORS_PATH.to_string());
+    }
+
+    let mut manifest = plan.manifest();
+
+    manifest.parameter(&args.join(" "));
+
+    manifest.parameter(&title);
+
+    for input in results_paths.iter().map(PathBuf::as_path).chain(args.get(1).map(Path::new)) {
+        manifest.input(input)?;
+    }
+
+    manifest.output(&html_path);
+
+    plan.add(format!("collate {}", html_path.display()), manifest, &html_path.clone(), move || {
+        let mut results = vec![];
+        for results_path in &results_paths {
+            results.extend(
+                std::fs::read(results_path).map_err(|e| format!("Error reading {}: {}", results_path.display(), e))?
+            );
+        }
+        let table = runner::run(&program, &args, Some(Box::new(std::io::Cursor::new(results))))?;
+        let html = format!(
+            "<html><head><title>{}</title><script src=\"sorttable.js\"></script></head><body><h1>{}</h1><p>Note: sort \
+             by column by clicking on the column header<p>\n{}</body></html>\n",
+            title, title, table
+        );
+        runner::write_file(&html_path, html.as_bytes())
+    })
+}
+
+// Fetches the given range of epochs, then processes them (with the default processing options), calculates their
+// results under each of the given credit policies, and collates those.  Failed epochs are left out of the later
+// stages, and all failures are summarized at the end.
+fn run(
+    args : &[String],
+    options : Options
+) -> Result<Failures, String>
+{
+    if args.len() < 5 {
+        return Err(USAGE.to_string());
+    }
+
+    // Checked up front, so that a mistyped policy is not found only after hours of fetching
+    for policy in &args[4..] {
+        fetch_data::credit_policy::parse(policy)?;
+    }
+
+    let mut fetch_args = args[0..3].to_vec();
+
+    if args[3] != "_" {
+        fetch_args.push(args[3].clone());
+    }
+
+    let mut failures = fetch(&fetch_args)?;
+
+    failures.extend(process(&[], options)?);
+
+    for policy in &args[4..] {
+        failures.extend(calculate(std::slice::from_ref(policy), options)?);
+    }
+
+    failures.extend(collate(options)?);
+
+    Ok(failures)
+}
+
+// The path of an epoch's file in one of the directories of layout.rs, e.g. data/600.gz
+fn epoch_path(
+    directory : &str,
+    epoch : u64,
+    suffix : &str
+) -> PathBuf
+{
+    Path::new(directory).join(format!("{}{}", epoch, suffix))
+}
+
+fn path_arg(path : &Path) -> String
+{
+    path.to_string_lossy().to_string()
+}
+
+// The path as an argument if the file exists, else "_" for none
+fn existing_path_arg(path : PathBuf) -> String
+{
+    if path.exists() {
+        path_arg(&path)
+    }
+    else {
+        "_".to_string()
+    }
+}
+
+fn create_directory(directory : &Path) -> Result<(), String>
+{
+    std::fs::create_dir_all(directory).map_err(|e| format!("Error creating {}: {}", directory.display(), e))
+}
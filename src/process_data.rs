@@ -1,8 +1,154 @@
+mod tower;
+
+use fetch_data::layout;
+use fetch_data::records::{self, Histogram, VoteLine};
+use fetch_data::runner::{self, Job};
+use fetch_data::scenario;
+use fetch_data::validators;
+use fetch_data::vote_stream;
 use std::collections::HashMap;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+
+// Minimum fraction of blocks that must have been fetched for an epoch to be processed, if not given
+const DEFAULT_MIN_COVERAGE : f64 = 0.99;
+
+// How the latency of a vote is measured: the number of slots from an origin slot to the slot the vote landed in
+#[derive(Clone, Copy, PartialEq)]
+enum LatencyOrigin
+{
+    // The origin is the slot after the voted slot, so that landing in the slot immediately after the voted slot is
+    // latency 0 (i.e. latency is (slot - 1) - voted_slot)
+    Landing,
+
+    // Latency is the landing block's parent slot - voted_slot, so that slots skipped by leaders just before the
+    // landing slot don't count
+    Parent,
+
+    // The origin is the first slot after the voted slot that produced a block, so that slots skipped by leaders just
+    // after the voted slot don't count
+    Block
+}
+
+impl std::str::FromStr for LatencyOrigin
+{
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err>
+    {
+        match s {
+            "landing" => Ok(LatencyOrigin::Landing),
+            "parent" => Ok(LatencyOrigin::Parent),
+            "block" => Ok(LatencyOrigin::Block),
+            _ => Err(format!("Invalid latency origin {} (must be \"landing\", \"parent\", or \"block\")", s))
+        }
+    }
+}
+
+// Which vote credits are counted
+#[derive(Clone, Copy, PartialEq)]
+enum CreditMode
+{
+    // Every newly voted slot of a landed vote counts as a credit, at the latency the vote landed with
+    Landed,
+
+    // Landed votes are replayed through a simulation of the validator's vote tower, and only voted slots that are
+    // rooted count as a credit, at the latency that was recorded for the slot when its vote landed.  This is what the
+    // vote program actually pays.  Each validator's tower is carried over from the previous epoch, so that slots voted
+    // at the end of an epoch are credited when they are rooted in the next.
+    Tower
+}
+
+impl std::str::FromStr for CreditMode
+{
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err>
+    {
+        match s {
+            "landed" => Ok(CreditMode::Landed),
+            "tower" => Ok(CreditMode::Tower),
+            _ => Err(format!("Invalid credit mode {} (must be \"landed\" or \"tower\")", s))
+        }
+    }
+}
+
+// The blocks of an epoch, loaded from the slot metadata written by fetch_data
+struct SlotMetadata
+{
+    // Map from slot to parent slot
+    parents : HashMap<u64, u64>,
 
-// xxx load validators.app validator data to get data center ID
-// xxx don't compute timely credits, just emit the number of votes with
-// latencies 1 - 64 for each validator
+    // All slots that produced a block, in order
+    slots : Vec<u64>
+}
+
+impl SlotMetadata
+{
+    // Loads slot metadata from a file (which may be compressed with gzip) whose lines, after a "#" header, are of the
+    // form:
+    // SLOT PARENT_SLOT ...
+    fn load(path : &String) -> Result<Self, String>
+    {
+        let mut contents = String::new();
+
+        records::open(Path::new(path))?
+            .read_to_string(&mut contents)
+            .map_err(|e| format!("Error reading slot metadata {}: {}", path, e))?;
+
+        let mut parents = HashMap::new();
+
+        for line in contents.lines().filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let split : Vec<&str> = line.split(' ').collect();
+            if split.len() < 2 {
+                return Err(format!("Invalid slot metadata line in {}: {}", path, line));
+            }
+            let slot =
+                split[0].parse::<u64>().map_err(|e| format!("Invalid slot metadata in {} ({}): {}", path, e, line))?;
+            let parent =
+                split[1].parse::<u64>().map_err(|e| format!("Invalid slot metadata in {} ({}): {}", path, e, line))?;
+            parents.insert(slot, parent);
+        }
+
+        let mut slots : Vec<u64> = parents.keys().cloned().collect();
+
+        slots.sort();
+
+        Ok(SlotMetadata { parents, slots })
+    }
+
+    // Returns the latency of a vote for voted_slot that landed in slot.  Where the slot metadata doesn't cover the
+    // slots involved (i.e. votes for slots of the previous epoch), latency is measured from the landing slot.  Invalid
+    // vote data, with a voted slot no older than the slot its vote landed in, gives latency 0 rather than underflowing.
+    fn latency(
+        &self,
+        origin : LatencyOrigin,
+        slot : u64,
+        voted_slot : u64
+    ) -> u64
+    {
+        let from_landing = slot.saturating_sub(1).saturating_sub(voted_slot);
+
+        match origin {
+            LatencyOrigin::Landing => from_landing,
+            LatencyOrigin::Parent => match self.parents.get(&slot) {
+                Some(parent) => parent.saturating_sub(voted_slot),
+                None => from_landing
+            },
+            LatencyOrigin::Block => {
+                if self.slots.first().is_none_or(|first| *first > voted_slot) {
+                    return from_landing;
+                }
+                // First slot after voted_slot that produced a block
+                let index = self.slots.partition_point(|produced| *produced <= voted_slot);
+                match self.slots.get(index) {
+                    Some(origin_slot) => slot.saturating_sub(*origin_slot),
+                    None => from_landing
+                }
+            }
+        }
+    }
+}
 
 struct VoteAccount
 {
@@ -12,131 +158,1310 @@ struct VoteAccount
 
     pub total_vote_credits : u32,
 
+    pub vote_latencies : Vec<u32>,
+
+    pub failed_transactions : u64,
+
+    // Map from error kind to number of failed transactions that failed with that kind of error
+    pub failures : HashMap<String, u64>,
+
+    // The simulated vote tower, for CreditMode::Tower, starting from the tower carried over from the previous epoch
+    pub tower : tower::Tower,
+
+    // Map from block index to the votes landed in that block of slots, when the epoch is split into blocks
+    pub blocks : HashMap<u64, BlockVotes>
+}
+
+// The votes of a vote account that landed in one block of slots
+struct BlockVotes
+{
+    pub total_transactions : u64,
+
+    pub total_vote_credits : u32,
+
     pub vote_latencies : Vec<u32>
 }
 
-// For serde json loading
-#[derive(serde::Deserialize)]
-struct ValidatorDetails
+impl VoteAccount
 {
-    pub vote_account : Option<String>,
+    fn new(
+        pubkey : String,
+        tower : tower::Tower
+    ) -> Self
+    {
+        VoteAccount {
+            pubkey,
+            total_transactions : 0_u64,
+            total_vote_credits : 0_u32,
+            vote_latencies : vec![0_u32; records::LATENCIES],
+            failed_transactions : 0_u64,
+            failures : HashMap::new(),
+            tower,
+            blocks : HashMap::new()
+        }
+    }
+}
+
+// How votes are processed, which is the same for every epoch
+struct Settings
+{
+    // Map from vote account to data center id
+    validator_data_centers : HashMap<String, String>,
+
+    min_coverage : f64,
+
+    latency_origin : LatencyOrigin,
+
+    credit_mode : CreditMode,
+
+    // If given, epochs are also split into blocks of this many slots
+    block_slots : Option<u64>,
 
-    pub data_center_key : Option<String>
+    // Number of threads that parse each epoch's text vote data
+    parsers : usize
 }
 
-fn load_json_file<T : for<'de> serde::de::Deserialize<'de>>(path : &String) -> Option<T>
+// The files that an epoch is processed with, and the files and directories that it is written to besides its
+// histograms, each of which is None for none
+struct EpochFiles
 {
-    std::fs::OpenOptions::new()
-        .read(true)
-        .open(path)
-        .map(|file| {
-            serde_json::from_reader(std::io::BufReader::new(file))
-                .map_err(|e| {
-                    eprintln!("Error reading JSON from {}:\n    {}\n", path, e);
-                    e
-                })
-                .ok()
+    failures : Option<String>,
+
+    gaps : Option<String>,
+
+    slots : Option<String>,
+
+    stakes : Option<String>,
+
+    // The directory that the blocks of slots are written into, when the epoch is split into blocks
+    blocks : Option<String>,
+
+    // In tower credit mode, the towers at the end of the previous epoch that the epoch's towers start from, and the
+    // file that the towers at the end of the epoch are written to
+    previous_towers : Option<String>,
+
+    towers : Option<String>
+}
+
+// The votes of an epoch as they are read, by vote account
+struct EpochVotes<'a>
+{
+    settings : &'a Settings,
+
+    slot_metadata : &'a SlotMetadata,
+
+    // The epoch, from the header, which the simulated vote towers credit votes to
+    epoch : u64,
+
+    // The first slot of the epoch, from the header, from which blocks of slots are counted
+    first_slot : u64,
+
+    // Map from the 32 bytes of a vote account to its votes
+    vote_accounts : HashMap<[u8; 32], VoteAccount>,
+
+    // Map from vote account to its tower at the end of the previous epoch, taken from as each vote account is first
+    // seen
+    previous_towers : HashMap<String, tower::Tower>
+}
+
+// Returns the fraction of slots listed as having blocks that were actually fetched, from a gap report written by
+// fetch_data, whose lines (after a "#" header) are of the form:
+// FIRST_SLOT LAST_SLOT CONFIRMED_SLOTS FETCHED_SLOTS MISSING_SLOT...
+fn load_coverage(path : &String) -> Result<f64, String>
+{
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Error reading gaps from {}: {}", path, e))?;
+
+    let mut total_confirmed = 0_u64;
+
+    let mut total_fetched = 0_u64;
+
+    for line in contents.lines().filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let split : Vec<&str> = line.split(' ').collect();
+        if split.len() < 4 {
+            return Err(format!("Invalid gaps line in {}: {}", path, line));
+        }
+        total_confirmed +=
+            split[2].parse::<u64>().map_err(|e| format!("Invalid gaps line in {} ({}): {}", path, e, line))?;
+        total_fetched +=
+            split[3].parse::<u64>().map_err(|e| format!("Invalid gaps line in {} ({}): {}", path, e, line))?;
+    }
+
+    if total_confirmed == 0 {
+        Ok(1_f64)
+    }
+    else {
+        Ok((total_fetched as f64) / (total_confirmed as f64))
+    }
+}
+
+// Loads a stake file, whose lines are of the form:
+// VOTE_ID ACTIVATED_STAKE
+// where ACTIVATED_STAKE is in lamports
+fn load_stakes(path : &String) -> Result<HashMap<String, u64>, String>
+{
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Error reading stakes from {}: {}", path, e))?;
+
+    contents
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once(' ') {
+            Some((vote_account, stake)) => stake
+                .parse::<u64>()
+                .map(|stake| (vote_account.to_string(), stake))
+                .map_err(|e| format!("Invalid stakes line in {} ({}): {}", path, e, line)),
+            None => Err(format!("Invalid stakes line in {}: {}", path, line))
         })
-        .map_err(|e| eprintln!("Error opening JSON file {} for read:\n    {}", path, e))
-        .ok()
-        .unwrap_or(None)
+        .collect()
 }
 
-fn main()
+// Loads the towers of vote accounts from a file of lines of the form:
+// VOTE_ACCOUNT TOWER
+// where TOWER is as described in tower.rs
+fn load_towers(path : &String) -> Result<HashMap<String, tower::Tower>, String>
 {
-    // Read validators.app file as first argument
-    // Map from pubkey to data center id
-    let validator_data_centers = {
-        let mut args = std::env::args();
-        args.nth(0);
-        if let Some(validator_info_file) = args.nth(0) {
-            let details : Option<Vec<ValidatorDetails>> = load_json_file(&validator_info_file);
-            match details {
-                Some(details) => details
-                    .into_iter()
-                    .filter(|d| d.vote_account.is_some() && d.data_center_key.is_some())
-                    .map(|d| (d.vote_account.unwrap(), d.data_center_key.unwrap()))
-                    .collect(),
-                None => std::process::exit(-1)
-            }
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Error reading towers from {}: {}", path, e))?;
+
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (vote_account, tower) =
+                line.split_once(' ').ok_or(format!("Invalid towers line in {}: {}", path, line))?;
+            let tower =
+                tower.parse::<tower::Tower>().map_err(|e| format!("Invalid tower in {} ({}): {}", path, e, line))?;
+            Ok((vote_account.to_string(), tower))
+        })
+        .collect()
+}
+
+// Writes towers in the form read by load_towers, in order of vote account
+fn write_towers(
+    path : &String,
+    towers : &[(&String, &tower::Tower)]
+) -> Result<(), String>
+{
+    let mut towers = towers.to_vec();
+
+    towers.sort_by(|a, b| a.0.cmp(b.0));
+
+    let contents : String =
+        towers.iter().map(|(vote_account, tower)| format!("{} {}\n", vote_account, tower)).collect();
+
+    runner::write_file(Path::new(path), contents.as_bytes())
+}
+
+// Processes every fetched epoch of a scenario's clusters, just as process_epochs does for a directory of vote data
+// (reading EPOCH.votes in preference to EPOCH.gz, one epoch per worker thread, with one per CPU), writing
+// data_processed, data_failures, and (if the scenario splits epochs into blocks) data_blocks under each cluster's
+// scenario directory.  Epochs that fail to process (e.g. for too little coverage) are reported and skipped.
+fn process_scenario(scenario_path : &String) -> Result<(), String>
+{
+    let scenario = scenario::load(scenario_path)?;
+
+    let workers = std::thread::available_parallelism().map(|workers| workers.get()).unwrap_or(1);
+
+    // The vote data of each cluster's epochs
+    let cluster_epochs = scenario
+        .clusters
+        .iter()
+        .map(|cluster| {
+            let data_directory = Path::new(&cluster.directory).join(layout::DATA);
+            Ok(list_vote_data(&data_directory)?.into_iter().filter(|(epoch, _)| cluster.includes(*epoch)).collect())
+        })
+        .collect::<Result<Vec<Vec<(u64, PathBuf)>>, String>>()?;
+
+    let parsers = parsers(workers.min(cluster_epochs.iter().map(Vec::len).sum()));
+
+    let mut jobs = vec![];
+
+    for (cluster, epochs) in scenario.clusters.iter().zip(cluster_epochs) {
+        let directory = scenario.directory(cluster);
+
+        for subdirectory in [layout::PROCESSED, layout::FAILURES, layout::TOWERS] {
+            std::fs::create_dir_all(directory.join(subdirectory))
+                .map_err(|e| format!("Error creating {}: {}", directory.join(subdirectory).display(), e))?;
+        }
+
+        let settings = std::sync::Arc::new(Settings {
+            validator_data_centers : load_data_centers(cluster.validators.as_deref().unwrap_or("_"))?,
+            min_coverage : scenario.processing.min_coverage,
+            latency_origin : scenario.processing.latency_origin.parse::<LatencyOrigin>()?,
+            credit_mode : scenario.processing.credit_mode.parse::<CreditMode>()?,
+            block_slots : scenario.processing.block_slots,
+            parsers
+        });
+
+        let mut previous = None;
+
+        for (epoch, data_path) in epochs {
+            let (previous_towers, towers, chain) = match settings.credit_mode {
+                CreditMode::Tower => {
+                    let (previous_towers, towers, chain) =
+                        chain_towers(&directory.join(layout::TOWERS), epoch, &mut previous);
+                    (previous_towers, Some(towers), Some(chain))
+                },
+                CreditMode::Landed => (None, None, None)
+            };
+            let files = EpochFiles {
+                failures : Some(directory.join(layout::FAILURES).join(epoch.to_string()).display().to_string()),
+                gaps : scenario.metadata_path(cluster, "data_gaps", epoch),
+                slots : scenario.metadata_path(cluster, "data_slots", epoch),
+                stakes : scenario.metadata_path(cluster, "data_stakes", epoch),
+                blocks : settings
+                    .block_slots
+                    .map(|_| directory.join(layout::BLOCKS).join(epoch.to_string()).display().to_string()),
+                previous_towers,
+                towers
+            };
+            jobs.push(epoch_job(
+                format!("process cluster {} epoch {} from {}", cluster.name, epoch, data_path.display()),
+                settings.clone(),
+                epoch,
+                data_path,
+                files,
+                directory.join(layout::PROCESSED).join(epoch.to_string()),
+                chain
+            ));
+        }
+    }
+
+    let failures = runner::run_jobs(jobs, workers);
+
+    if !failures.is_empty() {
+        eprintln!("Skipped {} epochs:", failures.len());
+        for (description, e) in &failures {
+            eprintln!("  {}: {}", description, e);
+        }
+    }
+
+    Ok(())
+}
+
+// Processes the vote data of many epochs in one process, on at most JOBS worker threads at once (or one per CPU, for
+// "_"), one epoch per worker so that memory use is bounded by the number of workers rather than the number of epochs,
+// with the CPUs left over parsing each epoch's text vote data.
+// Arguments are:
+//
+// JOBS VALIDATORS MIN_COVERAGE LATENCY_ORIGIN CREDIT_MODE BLOCK_SLOTS INPUT...
+//
+// where VALIDATORS, MIN_COVERAGE, LATENCY_ORIGIN, and CREDIT_MODE are as for a single epoch, BLOCK_SLOTS is the number
+// of slots of each block (or _ for none), and each INPUT is either a file of vote data named after its epoch (EPOCH.gz,
+// EPOCH.votes, or just EPOCH), or a directory of them such as "data", in which EPOCH.votes is read in preference to
+// EPOCH.gz.  Vote data may be text or a vote stream, and may be compressed with gzip, and an epoch whose vote data's
+// header is of another epoch fails.  The other files of each epoch are found in, and written to, the directories of
+// layout.rs beside the directory that its vote data is in: the gaps, slots, and stakes are used if they exist, and
+// data_processed/EPOCH, data_failures/EPOCH, and (with BLOCK_SLOTS) data_blocks/EPOCH are written.  Returns the epoch
+// and error of every epoch that failed, whose processed output is removed.
+fn process_epochs(args : &[String]) -> Result<Vec<(String, String)>, String>
+{
+    let [jobs, validators, min_coverage, latency_origin, credit_mode, block_slots, inputs @ ..] = args
+    else {
+        return Err("Usage: process_data epochs JOBS VALIDATORS MIN_COVERAGE LATENCY_ORIGIN CREDIT_MODE BLOCK_SLOTS \
+                    INPUT..."
+            .to_string());
+    };
+
+    let workers = match jobs.as_str() {
+        "_" => std::thread::available_parallelism().map(|workers| workers.get()).unwrap_or(1),
+        jobs => jobs
+            .parse::<usize>()
+            .ok()
+            .filter(|workers| *workers > 0)
+            .ok_or(format!("Invalid number of jobs {} (must be at least 1, or _)", jobs))?
+    };
+
+    let mut settings = Settings {
+        validator_data_centers : load_data_centers(validators)?,
+        min_coverage : min_coverage.parse::<f64>().map_err(|e| format!("Invalid minimum coverage: {}", e))?,
+        latency_origin : latency_origin.parse::<LatencyOrigin>()?,
+        credit_mode : credit_mode.parse::<CreditMode>()?,
+        block_slots : match block_slots.as_str() {
+            "_" => None,
+            block_slots => Some(
+                block_slots
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|block_slots| *block_slots > 0)
+                    .ok_or(format!("Invalid block slots {} (must be at least 1, or _)", block_slots))?
+            )
+        },
+        parsers : 1
+    };
+
+    // Map from epoch to (path of its vote data, directory of its other files)
+    let mut epochs = std::collections::BTreeMap::<u64, (PathBuf, PathBuf)>::new();
+
+    for input in inputs {
+        let input = Path::new(input);
+        let found = if input.is_dir() {
+            list_vote_data(input)?.into_iter().map(|(epoch, path)| (epoch, path, base_directory(input))).collect()
         }
         else {
-            HashMap::<String, String>::new()
+            let file_name = input.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let epoch = file_name
+                .strip_suffix(".gz")
+                .or(file_name.strip_suffix(layout::VOTE_STREAM_SUFFIX))
+                .unwrap_or(&file_name)
+                .parse::<u64>()
+                .map_err(|_| format!("{} is not named after its epoch", input.display()))?;
+            vec![(epoch, input.to_path_buf(), base_directory(input.parent().unwrap_or(Path::new(""))))]
+        };
+        for (epoch, path, base) in found {
+            if epochs.insert(epoch, (path, base)).is_some() {
+                return Err(format!("Epoch {} is given more than once", epoch));
+            }
+        }
+    }
+
+    settings.parsers = parsers(workers.min(epochs.len()));
+
+    let settings = std::sync::Arc::new(settings);
+
+    let mut output_directories : Vec<PathBuf> = vec![];
+
+    for (_, base) in epochs.values() {
+        output_directories.push(base.join(layout::PROCESSED));
+        output_directories.push(base.join(layout::FAILURES));
+        if settings.block_slots.is_some() {
+            output_directories.push(base.join(layout::BLOCKS));
+        }
+        if settings.credit_mode == CreditMode::Tower {
+            output_directories.push(base.join(layout::TOWERS));
+        }
+    }
+
+    output_directories.sort();
+
+    output_directories.dedup();
+
+    for directory in output_directories {
+        std::fs::create_dir_all(&directory).map_err(|e| format!("Error creating {}: {}", directory.display(), e))?;
+    }
+
+    let mut jobs = vec![];
+
+    let mut previous = None;
+
+    for (epoch, (data_path, base)) in epochs {
+        let existing = |directory : &str, suffix : &str| {
+            let path = base.join(directory).join(format!("{}{}", epoch, suffix));
+            path.is_file().then(|| path.display().to_string())
+        };
+        let (previous_towers, towers, chain) = match settings.credit_mode {
+            CreditMode::Tower => {
+                let (previous_towers, towers, chain) = chain_towers(&base.join(layout::TOWERS), epoch, &mut previous);
+                (previous_towers, Some(towers), Some(chain))
+            },
+            CreditMode::Landed => (None, None, None)
+        };
+        let files = EpochFiles {
+            failures : Some(base.join(layout::FAILURES).join(epoch.to_string()).display().to_string()),
+            gaps : existing(layout::GAPS, ""),
+            slots : existing(layout::SLOTS, ".gz"),
+            stakes : existing(layout::STAKES, ""),
+            blocks : settings
+                .block_slots
+                .map(|_| base.join(layout::BLOCKS).join(epoch.to_string()).display().to_string()),
+            previous_towers,
+            towers
+        };
+        jobs.push(epoch_job(
+            format!("process epoch {} from {}", epoch, data_path.display()),
+            settings.clone(),
+            epoch,
+            data_path,
+            files,
+            base.join(layout::PROCESSED).join(epoch.to_string()),
+            chain
+        ));
+    }
+
+    Ok(runner::run_jobs(jobs, workers))
+}
+
+// Lists the epochs of a directory of vote data such as "data", in increasing order, with the path of each epoch's
+// vote data
+fn list_vote_data(directory : &Path) -> Result<Vec<(u64, PathBuf)>, String>
+{
+    let mut found = vec![];
+
+    for epoch in layout::list_epochs(directory, ".gz")? {
+        found.push((epoch, directory.join(format!("{}.gz", epoch))));
+    }
+
+    // Epochs converted to vote streams are read from those, which is faster
+    for epoch in layout::list_epochs(directory, layout::VOTE_STREAM_SUFFIX)? {
+        found.retain(|(found_epoch, _)| *found_epoch != epoch);
+        found.push((epoch, directory.join(format!("{}{}", epoch, layout::VOTE_STREAM_SUFFIX))));
+    }
+
+    found.sort();
+
+    Ok(found)
+}
+
+// How an epoch processed in tower credit mode follows on from the epoch before it, whose towers it starts from
+struct TowerChain
+{
+    // If the previous epoch is processed in the same run, tells whether it succeeded once it has finished
+    previous : Option<std::sync::mpsc::Receiver<bool>>,
+
+    // Tells the next epoch whether this one succeeded
+    next : std::sync::mpsc::Sender<bool>
+}
+
+// Returns the previous towers, towers, and chain of an epoch processed in tower credit mode, whose towers are kept in
+// the given directory.  The epoch starts from the towers of the previous epoch, once they are written if the previous
+// epoch is processed in the same run, or as an earlier run wrote them, or else from empty towers.  Previous is the
+// epoch processed before this one in the same run, if any, with the receiver of its chain, and is replaced by this
+// epoch.
+fn chain_towers(
+    towers_directory : &Path,
+    epoch : u64,
+    previous : &mut Option<(u64, std::sync::mpsc::Receiver<bool>)>
+) -> (Option<String>, String, TowerChain)
+{
+    let wait =
+        previous.take().filter(|(previous_epoch, _)| (previous_epoch + 1) == epoch).map(|(_, finished)| finished);
+
+    let previous_towers = epoch
+        .checked_sub(1)
+        .map(|previous_epoch| towers_directory.join(previous_epoch.to_string()))
+        .filter(|path| wait.is_some() || path.is_file())
+        .map(|path| path.display().to_string());
+
+    let (next, finished) = std::sync::mpsc::channel();
+
+    *previous = Some((epoch, finished));
+
+    (previous_towers, towers_directory.join(epoch.to_string()).display().to_string(), TowerChain {
+        previous : wait,
+        next
+    })
+}
+
+// A job that processes the vote data of an epoch and writes its histograms to processed_path, which is removed if the
+// epoch fails.  In tower credit mode, the job first waits for the previous epoch of the chain, and fails if that did.
+fn epoch_job(
+    description : String,
+    settings : std::sync::Arc<Settings>,
+    epoch : u64,
+    data_path : PathBuf,
+    files : EpochFiles,
+    processed_path : PathBuf,
+    chain : Option<TowerChain>
+) -> Job
+{
+    Job {
+        description,
+        work : Box::new(move || {
+            // Blocks from an earlier run with different block slots would otherwise be left behind
+            if let Some(blocks) = &files.blocks {
+                let _ = std::fs::remove_dir_all(blocks);
+            }
+            let previous_failed = chain
+                .as_ref()
+                .and_then(|chain| chain.previous.as_ref())
+                .is_some_and(|previous| !previous.recv().unwrap_or(false));
+            let result = if previous_failed {
+                Err(format!("Epoch {} failed, so there are no towers for this epoch to start from", epoch - 1))
+            }
+            else {
+                ChunkReader::open(&data_path)
+                    .and_then(|input| {
+                        process_epoch(&settings, &files, input, &data_path.display().to_string(), Some(epoch), false)
+                    })
+                    .and_then(|processed| runner::write_file(&processed_path, processed.as_bytes()))
+            };
+            if let Some(chain) = &chain {
+                let _ = chain.next.send(result.is_ok());
+            }
+            if result.is_err() {
+                // Output of an earlier run would otherwise be taken for this one's
+                let _ = std::fs::remove_file(&processed_path);
+                if let Some(towers) = &files.towers {
+                    let _ = std::fs::remove_file(towers);
+                }
+            }
+            result
+        })
+    }
+}
+
+// Number of threads to parse each epoch's text vote data on, sharing the CPUs between the epochs processed at once
+fn parsers(epochs_at_once : usize) -> usize
+{
+    let cpus = std::thread::available_parallelism().map(|cpus| cpus.get()).unwrap_or(1);
+
+    std::cmp::max(cpus / std::cmp::max(epochs_at_once, 1), 1)
+}
+
+// The directory that the other files of an epoch are in, given the directory that its vote data is in
+fn base_directory(data_directory : &Path) -> PathBuf
+{
+    match data_directory.parent() {
+        Some(base) => base.to_path_buf(),
+        None => data_directory.join("..")
+    }
+}
+
+// Loads the map from vote account to data center id from a validators.app file (or _ for none)
+fn load_data_centers(validators_path : &str) -> Result<HashMap<String, String>, String>
+{
+    if validators_path == "_" {
+        return Ok(HashMap::new());
+    }
+
+    Ok(validators::load_validator_details(&validators_path.to_string())?
+        .into_iter()
+        .filter(|d| d.vote_account.is_some() && d.data_center_key.is_some())
+        .map(|d| (d.vote_account.unwrap(), d.data_center_key.unwrap()))
+        .collect())
+}
+
+// Processes the vote data of one epoch, which is either text vote data or a binary vote stream (see vote_stream.rs),
+// writing its failures and blocks of slots if asked to, and returning its histograms.  Source names the input in
+// errors.  If epoch is given, the input's header must be of that epoch, e.g. the one that its file is named after.  If
+// progress is true, progress is reported to stderr.
+fn process_epoch(
+    settings : &Settings,
+    files : &EpochFiles,
+    input : impl BufRead,
+    source : &str,
+    epoch : Option<u64>,
+    progress : bool
+) -> Result<String, String>
+{
+    if let Some(gaps_path) = &files.gaps {
+        let coverage = load_coverage(gaps_path)?;
+
+        if coverage < settings.min_coverage {
+            return Err(format!(
+                "Coverage {} in {} is below the minimum of {}, refusing to process",
+                coverage, gaps_path, settings.min_coverage
+            ));
         }
+
+        if progress {
+            eprintln!("Coverage is {}", coverage);
+        }
+    }
+
+    let slot_metadata = match &files.slots {
+        Some(slots_path) => SlotMetadata::load(slots_path)?,
+        None if settings.latency_origin == LatencyOrigin::Landing => {
+            SlotMetadata { parents : HashMap::new(), slots : vec![] }
+        },
+        None => return Err("Latency origins other than \"landing\" require slot metadata".to_string())
     };
 
-    // Read epoch data from stdin
-    let mut vote_accounts = HashMap::<String, VoteAccount>::new();
+    let stakes = files.stakes.as_ref().map(load_stakes).transpose()?;
 
-    let stdin = std::io::stdin();
+    let mut votes = EpochVotes {
+        settings,
+        slot_metadata : &slot_metadata,
+        epoch : 0,
+        first_slot : 0,
+        vote_accounts : HashMap::new(),
+        previous_towers : files.previous_towers.as_ref().map(load_towers).transpose()?.unwrap_or_default()
+    };
+
+    let mut lines_processed = 0_u64;
+
+    let mut report = || {
+        lines_processed += 1;
+        if progress && lines_processed.is_multiple_of(1000000) {
+            eprintln!("Processed {} lines", lines_processed);
+        }
+    };
+
+    // Vote data of the wrong epoch would otherwise be credited and written out as the epoch it was taken for
+    let check_epoch = |header_epoch : u64| match epoch {
+        Some(epoch) if epoch != header_epoch => {
+            Err(format!("{} holds vote data of epoch {}, not of epoch {}", source, header_epoch, epoch))
+        },
+        _ => Ok(())
+    };
+
+    // The input is either text vote data, or a binary vote stream, which is told by how it starts
+    let (is_vote_stream, input) = vote_stream::detect(input).map_err(|e| format!("Error reading {}: {}", source, e))?;
+
+    if is_vote_stream {
+        // Vote accounts are read as their 32 bytes, and are only put into base58 once each
+        let mut reader = vote_stream::Reader::new(input).map_err(|e| format!("{}: {}", source, e))?;
+        check_epoch(reader.header().epoch)?;
+        votes.epoch = reader.header().epoch;
+        votes.first_slot = reader.header().first_slot;
+        while let Some(record) = reader.next_record().map_err(|e| format!("{}: {}", source, e))? {
+            match record {
+                vote_stream::Record::Landed { slot, vote_account, voted_slots } => votes.landed(
+                    slot,
+                    reader.vote_account(vote_account),
+                    || reader.vote_account_name(vote_account).to_string(),
+                    &voted_slots
+                ),
+                vote_stream::Record::Failed { vote_account, error_kind, .. } => votes.failed(
+                    reader.vote_account(vote_account),
+                    || reader.vote_account_name(vote_account).to_string(),
+                    reader.error_kind(error_kind)
+                )
+            }
+            report();
+        }
+    }
+    else {
+        parse_vote_lines(input, source, settings.parsers, |line, decoded| {
+            match line {
+                // The header only gives the epoch and first slot
+                VoteLine::Header(header) => {
+                    check_epoch(header.epoch)?;
+                    votes.epoch = header.epoch;
+                    votes.first_slot = header.first_slot;
+                },
+                VoteLine::Landed { slot, vote_account, voted_slots } => {
+                    votes.landed(slot, &decoded, || vote_account, &voted_slots)
+                },
+                VoteLine::Failed { vote_account, error_kind, .. } => {
+                    votes.failed(&decoded, || vote_account, &error_kind)
+                },
+            }
+            report();
+            Ok(())
+        })?;
+    }
 
-    let mut lines_processed = 0;
+    let vote_accounts : Vec<VoteAccount> = votes.vote_accounts.into_values().collect();
 
-    loop {
-        let mut line = String::new();
+    if let Some(towers_path) = &files.towers {
+        // The towers of vote accounts that landed no votes in the epoch are carried over unchanged
+        let towers : Vec<(&String, &tower::Tower)> =
+            vote_accounts.iter().map(|va| (&va.pubkey, &va.tower)).chain(votes.previous_towers.iter()).collect();
+        write_towers(towers_path, &towers)?;
+    }
 
-        if stdin.read_line(&mut line).is_err() {
-            break;
+    if let Some(failures_path) = &files.failures {
+        // Lines are of the form:
+        // VOTE_ID TOTAL_TRANSACTIONS FAILED_TRANSACTIONS FAILURE_RATE ERROR_KIND=COUNT...
+        // where TOTAL_TRANSACTIONS includes the failed ones, and the ERROR_KINDs are in order of decreasing COUNT
+        let mut contents = String::new();
+        for va in &vote_accounts {
+            let total_transactions = va.total_transactions + va.failed_transactions;
+            contents.push_str(
+                format!(
+                    "{} {} {} {}",
+                    va.pubkey,
+                    total_transactions,
+                    va.failed_transactions,
+                    (va.failed_transactions as f64) / (total_transactions as f64)
+                )
+                .as_str()
+            );
+            let mut failures : Vec<(&String, &u64)> = va.failures.iter().collect();
+            failures.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (error_kind, count) in failures {
+                contents.push_str(format!(" {}={}", error_kind, count).as_str());
+            }
+            contents.push('\n');
         }
+        std::fs::write(failures_path, contents)
+            .map_err(|e| format!("Error writing failures to {}: {}", failures_path, e))?;
+    }
 
-        line.truncate(line.len() - 1);
+    // Lines are histograms (see records.rs)
+    let output_line =
+        |pubkey : &String, total_transactions : u64, total_vote_credits : u32, vote_latencies : &[u32]| {
+            let mut data_center = settings.validator_data_centers.get(pubkey).unwrap_or(&"_".to_string()).clone();
+            data_center.retain(|c| !c.is_whitespace());
+            Histogram {
+                data_center,
+                vote_account : pubkey.clone(),
+                total_transactions,
+                total_credits : total_vote_credits as u64,
+                latencies : vote_latencies.iter().map(|count| *count as u64).collect(),
+                stake : stakes.as_ref().map(|stakes| *stakes.get(pubkey).unwrap_or(&0))
+            }
+            .to_string()
+        };
 
-        if line.len() == 0 {
-            break;
+    if let Some(directory) = &files.blocks {
+        // Map from block index to the contents of its file
+        let mut block_contents = HashMap::<u64, String>::new();
+        for va in &vote_accounts {
+            for (block, block_votes) in &va.blocks {
+                let contents = block_contents.entry(*block).or_default();
+                contents.push_str(&output_line(
+                    &va.pubkey,
+                    block_votes.total_transactions,
+                    block_votes.total_vote_credits,
+                    &block_votes.vote_latencies
+                ));
+                contents.push('\n');
+            }
+        }
+        std::fs::create_dir_all(directory)
+            .map_err(|e| format!("Error creating blocks directory {}: {}", directory, e))?;
+        for (block, contents) in block_contents {
+            let block_path = Path::new(directory).join(block.to_string());
+            std::fs::write(&block_path, contents)
+                .map_err(|e| format!("Error writing block to {}: {}", block_path.display(), e))?;
         }
+    }
 
-        let mut split : Vec<&str> = line.split(" ").collect();
+    let mut processed = String::new();
 
-        let slot = split.remove(0).parse::<u64>().unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            std::process::exit(-1);
+    // Vote accounts which landed no votes at all are left out, as they were only seen in failed transactions
+    for va in vote_accounts.iter().filter(|va| va.total_transactions > 0) {
+        processed.push_str(&output_line(&va.pubkey, va.total_transactions, va.total_vote_credits, &va.vote_latencies));
+        processed.push('\n');
+    }
+
+    Ok(processed)
+}
+
+impl EpochVotes<'_>
+{
+    // Counts a landed vote transaction of the vote account with the given 32 bytes, whose base58 form is only made
+    // the first time that it is seen
+    fn landed(
+        &mut self,
+        slot : u64,
+        vote_account : &[u8; 32],
+        pubkey : impl FnOnce() -> String,
+        voted_slots : &[u64]
+    )
+    {
+        let va = self.vote_accounts.entry(*vote_account).or_insert_with(|| {
+            let pubkey = pubkey();
+            let tower = self.previous_towers.remove(&pubkey).unwrap_or_else(tower::Tower::new);
+            VoteAccount::new(pubkey, tower)
         });
 
-        let vote_pubkey = split.remove(0);
+        va.total_transactions += 1;
 
-        let mut to_insert = match vote_accounts.remove(vote_pubkey) {
-            Some(to_insert) => to_insert,
-            None => VoteAccount {
-                pubkey : vote_pubkey.to_string(),
+        let mut block_votes = self.settings.block_slots.map(|block_slots| {
+            va.blocks.entry(slot.saturating_sub(self.first_slot) / block_slots).or_insert_with(|| BlockVotes {
                 total_transactions : 0_u64,
                 total_vote_credits : 0_u32,
-                vote_latencies : vec![0_u32; 64]
+                vote_latencies : vec![0_u32; records::LATENCIES]
+            })
+        });
+
+        if let Some(block_votes) = block_votes.as_mut() {
+            block_votes.total_transactions += 1;
+        }
+
+        for voted_slot in voted_slots {
+            // Latency is number of slots past the "minimum possible vote slot", as determined by the latency origin
+            let latency = self.slot_metadata.latency(self.settings.latency_origin, slot, *voted_slot);
+            let credited_latency = match self.settings.credit_mode {
+                CreditMode::Landed => Some(latency),
+                CreditMode::Tower => va.tower.vote(self.epoch, *voted_slot, latency)
+            };
+            if let Some(latency) = credited_latency {
+                va.total_vote_credits += 1;
+                if latency < (va.vote_latencies.len() as u64) {
+                    va.vote_latencies[latency as usize] += 1;
+                }
+                if let Some(block_votes) = block_votes.as_mut() {
+                    block_votes.total_vote_credits += 1;
+                    if latency < (block_votes.vote_latencies.len() as u64) {
+                        block_votes.vote_latencies[latency as usize] += 1;
+                    }
+                }
             }
-        };
+        }
+    }
+
+    // Counts a failed vote transaction of the vote account with the given 32 bytes
+    fn failed(
+        &mut self,
+        vote_account : &[u8; 32],
+        pubkey : impl FnOnce() -> String,
+        error_kind : &str
+    )
+    {
+        let va = self.vote_accounts.entry(*vote_account).or_insert_with(|| {
+            let pubkey = pubkey();
+            let tower = self.previous_towers.remove(&pubkey).unwrap_or_else(tower::Tower::new);
+            VoteAccount::new(pubkey, tower)
+        });
+
+        va.failed_transactions += 1;
+
+        match va.failures.get_mut(error_kind) {
+            Some(count) => *count += 1,
+            None => {
+                va.failures.insert(error_kind.to_string(), 1);
+            }
+        }
+    }
+}
+
+// Number of lines of text vote data handed to a parser thread at once
+const PARSE_BATCH_LINES : usize = 4096;
+
+// Number of batches per parser thread that may be parsed ahead of the batch being counted
+const BATCHES_IN_FLIGHT_PER_PARSER : usize = 4;
+
+// The lines of a batch, each with its line number, and where to send them once parsed
+type ParseBatch = (Vec<(usize, String)>, std::sync::mpsc::SyncSender<Result<Vec<(VoteLine, [u8; 32])>, String>>);
+
+// Parses text vote data on the given number of threads, a batch of lines at a time, and hands each line to each, in
+// order, with the 32 bytes of its vote account (all zero for the header).  Each thread decodes a base58 vote account
+// only the first time that it sees it.  Errors name the source and the line number, as records::read does.
+fn parse_vote_lines(
+    input : impl BufRead,
+    source : &str,
+    parsers : usize,
+    mut each : impl FnMut(VoteLine, [u8; 32]) -> Result<(), String>
+) -> Result<(), String>
+{
+    let (batch_sender, batches) = std::sync::mpsc::channel::<ParseBatch>();
+
+    let batches = std::sync::Mutex::new(batches);
+
+    let batches = &batches;
+
+    // The parser threads stop once batch_sender, which this closure owns, is dropped on the way out of it
+    std::thread::scope(move |scope| {
+        for _ in 0..parsers {
+            scope.spawn(move || {
+                // Map from base58 vote account to its 32 bytes
+                let mut decoded = HashMap::<String, [u8; 32]>::new();
+                while let Ok((lines, parsed_sender)) = batches.lock().unwrap().recv() {
+                    let parsed = lines
+                        .into_iter()
+                        .map(|(line_number, line)| {
+                            parse_vote_line(&line, &mut decoded)
+                                .map_err(|e| format!("Invalid {} line {} ({}): {}", source, line_number, e, line))
+                        })
+                        .collect();
+                    let _ = parsed_sender.send(parsed);
+                }
+            });
+        }
+
+        // Batches handed out, in order, which are counted as soon as they are parsed
+        let mut pending = std::collections::VecDeque::new();
+
+        let mut batch = vec![];
+
+        let mut lines = input.lines();
+
+        let mut line_number = 0;
+
+        loop {
+            let done = match lines.next() {
+                Some(Ok(line)) => {
+                    line_number += 1;
+                    if !line.trim().is_empty() {
+                        batch.push((line_number, line));
+                    }
+                    false
+                },
+                Some(Err(e)) => return Err(format!("Error reading {} line {}: {}", source, line_number + 1, e)),
+                None => true
+            };
+
+            if (batch.len() == PARSE_BATCH_LINES) || (done && !batch.is_empty()) {
+                let (parsed_sender, parsed) = std::sync::mpsc::sync_channel(1);
+                batch_sender
+                    .send((std::mem::take(&mut batch), parsed_sender))
+                    .map_err(|_| format!("Error parsing {}: parser threads stopped", source))?;
+                pending.push_back(parsed);
+            }
+
+            while pending.len() > (if done { 0 } else { parsers * BATCHES_IN_FLIGHT_PER_PARSER }) {
+                let parsed = pending
+                    .pop_front()
+                    .unwrap()
+                    .recv()
+                    .map_err(|_| format!("Error parsing {}: parser thread stopped", source))??;
+                for (line, decoded) in parsed {
+                    each(line, decoded)?;
+                }
+            }
+
+            if done {
+                return Ok(());
+            }
+        }
+    })
+}
+
+// Parses a line of text vote data, decoding its vote account with the help of a map from the base58 vote accounts
+// already decoded to their 32 bytes
+fn parse_vote_line(
+    line : &str,
+    decoded : &mut HashMap<String, [u8; 32]>
+) -> Result<(VoteLine, [u8; 32]), String>
+{
+    let line = <VoteLine as records::Record>::parse(line)?;
+
+    let vote_account = match &line {
+        VoteLine::Header(_) => return Ok((line, [0_u8; 32])),
+        VoteLine::Landed { vote_account, .. } | VoteLine::Failed { vote_account, .. } => vote_account
+    };
+
+    let bytes = match decoded.get(vote_account) {
+        Some(bytes) => *bytes,
+        None => {
+            let bytes = vote_stream::parse_vote_account(vote_account)?;
+            decoded.insert(vote_account.clone(), bytes);
+            bytes
+        }
+    };
+
+    Ok((line, bytes))
+}
+
+// Reads a file of vote data on a thread of its own, which decompresses it if it was compressed with gzip and hands it
+// over a chunk at a time, so that decompression runs alongside processing.  At most CHUNKS_IN_FLIGHT chunks are held
+// at once.
+struct ChunkReader
+{
+    chunks : std::sync::mpsc::Receiver<Result<Vec<u8>, String>>,
+
+    chunk : Vec<u8>,
+
+    // How much of chunk has been consumed
+    position : usize
+}
+
+const CHUNK_SIZE : usize = 1024 * 1024;
+
+const CHUNKS_IN_FLIGHT : usize = 4;
+
+impl ChunkReader
+{
+    fn open(path : &Path) -> Result<Self, String>
+    {
+        let mut input = records::open(path)?;
+
+        let path = path.display().to_string();
 
-        to_insert.total_transactions += 1;
+        let (sender, chunks) = std::sync::mpsc::sync_channel(CHUNKS_IN_FLIGHT);
 
-        for voted_slot in split {
-            to_insert.total_vote_credits += 1;
-            // Latency is number of slots past the "minimum possible vote slot"
-            let latency = (slot - 1) -
-                voted_slot.parse::<u64>().unwrap_or_else(|e| {
-                    eprintln!("{} for [{}]", e, voted_slot);
+        // The thread stops at the end of the file, or when the reader is dropped
+        std::thread::spawn(move || loop {
+            let mut chunk = vec![0_u8; CHUNK_SIZE];
+            let chunk = match input.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => {
+                    chunk.truncate(read);
+                    Ok(chunk)
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(format!("Error reading {}: {}", path, e))
+            };
+            let failed = chunk.is_err();
+            if sender.send(chunk).is_err() || failed {
+                break;
+            }
+        });
+
+        Ok(ChunkReader { chunks, chunk : vec![], position : 0 })
+    }
+}
+
+impl Read for ChunkReader
+{
+    fn read(
+        &mut self,
+        buffer : &mut [u8]
+    ) -> std::io::Result<usize>
+    {
+        let available = self.fill_buf()?;
+
+        let read = available.len().min(buffer.len());
+
+        buffer[..read].copy_from_slice(&available[..read]);
+
+        self.consume(read);
+
+        Ok(read)
+    }
+}
+
+impl BufRead for ChunkReader
+{
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]>
+    {
+        if self.position == self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                },
+                Ok(Err(e)) => return Err(std::io::Error::other(e)),
+                // The end of the file
+                Err(_) => return Ok(&[])
+            }
+        }
+
+        Ok(&self.chunk[self.position..])
+    }
+
+    fn consume(
+        &mut self,
+        amount : usize
+    )
+    {
+        self.position = (self.position + amount).min(self.chunk.len());
+    }
+}
+
+fn main()
+{
+    let mut args = std::env::args();
+    args.next();
+
+    // Instead of the arguments below, may be "scenario" and the path of a scenario file (see scenario.rs), to process
+    // every fetched epoch of the scenario's clusters
+    if std::env::args().nth(1).as_deref() == Some("scenario") {
+        let scenario_path = std::env::args().nth(2).unwrap_or_else(|| {
+            eprintln!("Scenario requires scenario path");
+            std::process::exit(-1);
+        });
+        process_scenario(&scenario_path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+        return;
+    }
+
+    // Or may be "epochs" followed by the arguments of process_epochs, to process many epochs at once
+    if std::env::args().nth(1).as_deref() == Some("epochs") {
+        let failures = process_epochs(&std::env::args().skip(2).collect::<Vec<String>>()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+        if !failures.is_empty() {
+            eprintln!("{} failed:", failures.len());
+            for (description, e) in &failures {
+                eprintln!("  {}: {}", description, e);
+            }
+            std::process::exit(-1);
+        }
+        return;
+    }
+
+    // Read validators.app file as first argument (or _ for none)
+    // Map from pubkey to data center id
+    let validator_data_centers = load_data_centers(&args.next().unwrap_or("_".to_string())).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    });
+
+    // Optional second argument is the path of a file to write the per-validator failed vote transaction breakdown to
+    // (or _ for none)
+    let failures = args.next().filter(|arg| arg != "_");
+
+    // Optional third argument is the path of the gap report written by fetch_data (or _ for none), and optional fourth
+    // argument is the minimum fraction of the slots listed as having blocks that must actually have been fetched.
+    // Epochs with less coverage than that are refused, since missing blocks look just like skipped slots and would
+    // inflate latencies.
+    let gaps = args.next().filter(|arg| arg != "_");
+
+    let min_coverage = match args.next() {
+        Some(min_coverage) => min_coverage.parse::<f64>().unwrap_or_else(|e| {
+            eprintln!("Fourth argument must be minimum coverage: {}", e);
+            std::process::exit(-1);
+        }),
+        None => DEFAULT_MIN_COVERAGE
+    };
+
+    // Optional fifth argument is the latency origin ("landing", "parent", or "block"; default "landing"), and sixth
+    // argument is the slot metadata written by fetch_data (or _ for none), which the "parent" and "block" origins
+    // require
+    let latency_origin = match args.next() {
+        Some(latency_origin) => latency_origin.parse::<LatencyOrigin>().unwrap_or_else(|e| {
+            eprintln!("Fifth argument must be latency origin: {}", e);
+            std::process::exit(-1);
+        }),
+        None => LatencyOrigin::Landing
+    };
+
+    let slots = args.next().filter(|arg| arg != "_");
+
+    if slots.is_none() && (latency_origin != LatencyOrigin::Landing) {
+        eprintln!("Sixth argument must be slot metadata, for latency origins other than \"landing\"");
+        std::process::exit(-1);
+    }
+
+    // Optional seventh argument is the credit mode ("landed" or "tower"; default "landed").  With "tower", the tower
+    // of each validator starts from the towers given by the tenth argument, as they were at the end of the previous
+    // epoch, or else starts out empty, so that no credits are earned for its first 31 voted slots.  Slots still in the
+    // tower at the end of the epoch are credited in whichever epoch they are rooted, as the vote program does.
+    let credit_mode = match args.next() {
+        Some(credit_mode) => credit_mode.parse::<CreditMode>().unwrap_or_else(|e| {
+            eprintln!("Seventh argument must be credit mode: {}", e);
+            std::process::exit(-1);
+        }),
+        None => CreditMode::Landed
+    };
+
+    // Optional eighth argument is a stake file giving the activated stake of each vote account in the epoch (or _ for
+    // none).  If given, each output line has the activated stake of the validator appended, which is 0 for vote
+    // accounts that are not in the stake file.
+    let stakes = args.next().filter(|arg| arg != "_");
+
+    // Optional ninth argument is of the form BLOCK_SLOTS:DIRECTORY (or _ for none).  If given, the epoch is also split
+    // into blocks of BLOCK_SLOTS slots, and the votes that landed in each block are written, in the same form as the
+    // output, into a file in DIRECTORY named after the index of the block within the epoch.
+    let (block_slots, blocks) = match args.next().filter(|arg| arg != "_") {
+        Some(blocks) => {
+            match blocks.split_once(':').map(|(block_slots, directory)| (block_slots.parse::<u64>(), directory)) {
+                Some((Ok(block_slots), directory)) if block_slots > 0 => {
+                    (Some(block_slots), Some(directory.to_string()))
+                },
+                _ => {
+                    eprintln!("Ninth argument must be BLOCK_SLOTS:DIRECTORY");
                     std::process::exit(-1);
-                });
-            if latency < (to_insert.vote_latencies.len() as u64) {
-                to_insert.vote_latencies[latency as usize] += 1;
+                }
             }
+        },
+        None => (None, None)
+    };
+
+    // Optional tenth argument is the towers at the end of the previous epoch that the towers start from, and eleventh
+    // argument is the file to write the towers at the end of this epoch to, for the next epoch (each _ for none), in
+    // the form described in load_towers.  Both are only for the "tower" credit mode.
+    let previous_towers = args.next().filter(|arg| arg != "_");
+
+    let towers = args.next().filter(|arg| arg != "_");
+
+    if (previous_towers.is_some() || towers.is_some()) && (credit_mode != CreditMode::Tower) {
+        eprintln!("Tenth and eleventh arguments are only for the \"tower\" credit mode");
+        std::process::exit(-1);
+    }
+
+    let settings = Settings {
+        validator_data_centers,
+        min_coverage,
+        latency_origin,
+        credit_mode,
+        block_slots,
+        parsers : parsers(1)
+    };
+
+    let files = EpochFiles { failures, gaps, slots, stakes, blocks, previous_towers, towers };
+
+    // Read epoch data from stdin
+    let processed =
+        process_epoch(&settings, &files, std::io::stdin().lock(), "input", None, true).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+
+    print!("{}", processed);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // Blocks at slots 100, 101, 104, and 105, each the parent of the next
+    fn slot_metadata() -> SlotMetadata
+    {
+        SlotMetadata {
+            parents : HashMap::from([(100, 99), (101, 100), (104, 101), (105, 104)]),
+            slots : vec![100, 101, 104, 105]
         }
+    }
 
-        vote_accounts.insert(vote_pubkey.to_string(), to_insert);
+    #[test]
+    fn latency_is_measured_from_each_origin()
+    {
+        let slot_metadata = slot_metadata();
 
-        lines_processed += 1;
+        // A vote for 101 that landed in 105, whose parent is 104; the first block after 101 is 104
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Landing, 105, 101), 3);
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Parent, 105, 101), 3);
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Block, 105, 101), 1);
 
-        if (lines_processed % 1000000) == 0 {
-            eprintln!("Processed {} lines", lines_processed);
+        // Landing in the slot right after the voted slot is latency 0 from every origin
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Landing, 101, 100), 0);
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Parent, 101, 100), 0);
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Block, 101, 100), 0);
+
+        // Slots that the metadata doesn't cover are measured from the landing slot
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Parent, 103, 90), 12);
+        assert_eq!(slot_metadata.latency(LatencyOrigin::Block, 103, 90), 12);
+    }
+
+    #[test]
+    fn latency_of_invalid_votes_does_not_underflow()
+    {
+        let slot_metadata = slot_metadata();
+
+        for origin in [LatencyOrigin::Landing, LatencyOrigin::Parent, LatencyOrigin::Block] {
+            // Voted slots at or after the slot the vote landed in, and a vote that landed in slot 0
+            assert_eq!(slot_metadata.latency(origin, 105, 105), 0);
+            assert_eq!(slot_metadata.latency(origin, 105, 200), 0);
+            assert_eq!(slot_metadata.latency(origin, 0, 0), 0);
+            assert_eq!(slot_metadata.latency(origin, 0, u64::MAX), 0);
+        }
+    }
+
+    const VOTE_ACCOUNTS : [&str; 2] =
+        ["BzCsyLCBTmgfnrqc3DgKZCZYkFjpeQHMUZF41ZRRnB7S", "3AMtGf9nEUGDPiNxARgaNzcowjcWuDBA1fLptbVjXRwE"];
+
+    // Parses text vote data on the given number of threads, returning the slot of each line that landed a vote
+    fn parsed_slots(
+        input : &str,
+        parsers : usize
+    ) -> Result<Vec<u64>, String>
+    {
+        let mut slots = vec![];
+
+        parse_vote_lines(input.as_bytes(), "input", parsers, |line, decoded| {
+            match line {
+                VoteLine::Landed { slot, vote_account, .. } => {
+                    assert_eq!(decoded, vote_stream::parse_vote_account(&vote_account).unwrap());
+                    slots.push(slot);
+                },
+                VoteLine::Failed { .. } => panic!("unexpected failed vote"),
+                VoteLine::Header(_) => assert_eq!(decoded, [0_u8; 32])
+            }
+            Ok(())
+        })?;
+
+        Ok(slots)
+    }
+
+    #[test]
+    fn vote_lines_are_parsed_in_order_on_many_threads()
+    {
+        // Several batches, with blank lines between them
+        let lines = (PARSE_BATCH_LINES * 5) + 7;
+
+        let mut input = "# 600 259200000 259631999\n".to_string();
+
+        for slot in 0..(lines as u64) {
+            input.push_str(&format!("{} {} {}\n", slot + 1, VOTE_ACCOUNTS[(slot % 2) as usize], slot));
+            if slot % 1000 == 0 {
+                input.push('\n');
+            }
+        }
+
+        let expected : Vec<u64> = (1..=(lines as u64)).collect();
+
+        for parsers in [1, 3] {
+            assert_eq!(parsed_slots(&input, parsers).unwrap(), expected);
         }
     }
 
-    let vote_accounts : Vec<VoteAccount> = vote_accounts.into_iter().map(|(_, vote_account)| vote_account).collect();
+    #[test]
+    fn vote_line_errors_give_the_line_number()
+    {
+        let input =
+            format!("# 600 259200000 259631999\n\n5 {} 3\n6 xx 4\n7 {} 5\n", VOTE_ACCOUNTS[0], VOTE_ACCOUNTS[1]);
 
-    for va in vote_accounts {
-        let mut data_center = validator_data_centers.get(&va.pubkey).unwrap_or(&"_".to_string()).clone();
-        data_center.retain(|c| !c.is_whitespace());
-        print!("{} {} {} {}", data_center, va.pubkey, va.total_transactions, va.total_vote_credits);
-        for latency in va.vote_latencies {
-            print!(" {}", latency);
+        for parsers in [1, 3] {
+            let e = parsed_slots(&input, parsers).unwrap_err();
+            assert!(e.starts_with("Invalid input line 4 ("), "{}", e);
         }
-        println!("");
     }
 }
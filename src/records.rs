@@ -0,0 +1,470 @@
+// Records of the space separated files that pass between the programs of the pipeline, one record per line.  Each
+// record is parsed with Record::parse and written with its Display, which give exactly the form described in
+// README.txt, so that what one program writes, the next can read.  read() reads a file of records, giving the line
+// number of any line that is invalid.
+
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Number of latencies that a histogram counts votes at, from 0 up to 63
+pub const LATENCIES : usize = 64;
+
+// The first two bytes of a file compressed with gzip
+const GZIP_MAGIC : &[u8] = &[0x1f, 0x8b];
+
+pub trait Record: Sized + fmt::Display
+{
+    // Parses a record from one line, without its line ending
+    fn parse(line : &str) -> Result<Self, String>;
+}
+
+// The header line of the files that fetch_data writes:
+// # EPOCH FIRST_SLOT LAST_SLOT
+#[derive(Clone, Copy)]
+pub struct EpochHeader
+{
+    pub epoch : u64,
+
+    pub first_slot : u64,
+
+    pub last_slot : u64
+}
+
+impl Record for EpochHeader
+{
+    fn parse(line : &str) -> Result<Self, String>
+    {
+        let split = fields(line.strip_prefix('#').ok_or("header must start with #")?);
+
+        match split.as_slice() {
+            [epoch, first_slot, last_slot] => Ok(EpochHeader {
+                epoch : field("epoch", epoch)?,
+                first_slot : field("first slot", first_slot)?,
+                last_slot : field("last slot", last_slot)?
+            }),
+            _ => Err("header must be # EPOCH FIRST_SLOT LAST_SLOT".to_string())
+        }
+    }
+}
+
+impl fmt::Display for EpochHeader
+{
+    fn fmt(
+        &self,
+        f : &mut fmt::Formatter
+    ) -> fmt::Result
+    {
+        write!(f, "# {} {} {}", self.epoch, self.first_slot, self.last_slot)
+    }
+}
+
+// One line of the vote data that fetch_data writes: the header, followed by a line for each vote transaction of the
+// form:
+// SLOT VOTE_ID VOTED_SLOT...   (for a vote transaction that landed, with the slots that it newly voted on)
+// SLOT VOTE_ID !ERROR_KIND     (for a vote transaction that failed, with the kind of error that it failed with)
+pub enum VoteLine
+{
+    Header(EpochHeader),
+
+    Landed
+    {
+        slot : u64,
+        vote_account : String,
+        voted_slots : Vec<u64>
+    },
+
+    Failed
+    {
+        slot : u64,
+        vote_account : String,
+        error_kind : String
+    }
+}
+
+impl Record for VoteLine
+{
+    fn parse(line : &str) -> Result<Self, String>
+    {
+        if line.starts_with('#') {
+            return Ok(VoteLine::Header(EpochHeader::parse(line)?));
+        }
+
+        let split = fields(line);
+
+        if split.len() < 2 {
+            return Err("expected SLOT VOTE_ID".to_string());
+        }
+
+        let slot = field("slot", split[0])?;
+
+        let vote_account = split[1].to_string();
+
+        if let Some(error_kind) = split.get(2).and_then(|s| s.strip_prefix('!')) {
+            if split.len() > 3 {
+                return Err("failed vote transaction must have only an error kind".to_string());
+            }
+            return Ok(VoteLine::Failed { slot, vote_account, error_kind : error_kind.to_string() });
+        }
+
+        let voted_slots =
+            split[2..].iter().map(|voted_slot| field("voted slot", voted_slot)).collect::<Result<_, _>>()?;
+
+        Ok(VoteLine::Landed { slot, vote_account, voted_slots })
+    }
+}
+
+impl fmt::Display for VoteLine
+{
+    fn fmt(
+        &self,
+        f : &mut fmt::Formatter
+    ) -> fmt::Result
+    {
+        match self {
+            VoteLine::Header(header) => write!(f, "{}", header),
+            VoteLine::Landed { slot, vote_account, voted_slots } => {
+                write!(f, "{} {}", slot, vote_account)?;
+                for voted_slot in voted_slots {
+                    write!(f, " {}", voted_slot)?;
+                }
+                Ok(())
+            },
+            VoteLine::Failed { slot, vote_account, error_kind } => {
+                write!(f, "{} {} !{}", slot, vote_account, error_kind)
+            },
+        }
+    }
+}
+
+// One line of process_data output, the votes of one validator in an epoch (or in a block of slots of an epoch):
+// DATA_CENTER VOTE_ID TOTAL_TRANSACTIONS TOTAL_VOTE_CREDITS LATENCY_COUNT... [ACTIVATED_STAKE]
+#[derive(Clone)]
+pub struct Histogram
+{
+    pub data_center : String,
+
+    pub vote_account : String,
+
+    pub total_transactions : u64,
+
+    pub total_credits : u64,
+
+    // Number of votes landed at each latency, LATENCIES of them
+    pub latencies : Vec<u64>,
+
+    // Activated stake, in lamports, if process_data was given stakes
+    pub stake : Option<u64>
+}
+
+impl Record for Histogram
+{
+    fn parse(line : &str) -> Result<Self, String>
+    {
+        let split = fields(line);
+
+        if split.len() != (4 + LATENCIES) && split.len() != (5 + LATENCIES) {
+            return Err(format!("expected {} or {} fields, not {}", 4 + LATENCIES, 5 + LATENCIES, split.len()));
+        }
+
+        Ok(Histogram {
+            data_center : split[0].to_string(),
+            vote_account : split[1].to_string(),
+            total_transactions : field("total transactions", split[2])?,
+            total_credits : field("total credits", split[3])?,
+            latencies : split[4..(4 + LATENCIES)]
+                .iter()
+                .map(|count| field("latency count", count))
+                .collect::<Result<_, _>>()?,
+            stake : split.get(4 + LATENCIES).map(|stake| field("stake", stake)).transpose()?
+        })
+    }
+}
+
+impl fmt::Display for Histogram
+{
+    fn fmt(
+        &self,
+        f : &mut fmt::Formatter
+    ) -> fmt::Result
+    {
+        write!(f, "{} {} {} {}", self.data_center, self.vote_account, self.total_transactions, self.total_credits)?;
+        for count in &self.latencies {
+            write!(f, " {}", count)?;
+        }
+        if let Some(stake) = self.stake {
+            write!(f, " {}", stake)?;
+        }
+        Ok(())
+    }
+}
+
+// One line of calculate_results output, the results of one validator or data center:
+// NAME VALIDATORS TRANSACTIONS CREDITS TIMELY_CREDITS AVG_LATENCY NORMAL_PCT TIMELY_PCT [STAKE STAKE_SHARE]
+#[derive(Clone)]
+pub struct ResultRow
+{
+    pub name : String,
+
+    pub validators : u64,
+
+    pub transactions : u64,
+
+    pub credits : u64,
+
+    pub timely_credits : u64,
+
+    pub avg_latency : f64,
+
+    pub normal_pct : f64,
+
+    pub timely_pct : f64,
+
+    // Activated stake, in lamports, and share of total stake, if the input had stakes
+    pub stake : Option<(u64, f64)>
+}
+
+impl Record for ResultRow
+{
+    fn parse(line : &str) -> Result<Self, String>
+    {
+        let split = fields(line);
+
+        let stake = match split.len() {
+            8 => None,
+            10 => Some((field("stake", split[8])?, field("stake share", split[9])?)),
+            _ => return Err(format!("expected 8 or 10 fields, not {}", split.len()))
+        };
+
+        Ok(ResultRow {
+            name : split[0].to_string(),
+            validators : field("validators", split[1])?,
+            transactions : field("transactions", split[2])?,
+            credits : field("credits", split[3])?,
+            timely_credits : field("timely credits", split[4])?,
+            avg_latency : field("avg latency", split[5])?,
+            normal_pct : field("normal pct", split[6])?,
+            timely_pct : field("timely pct", split[7])?,
+            stake
+        })
+    }
+}
+
+impl fmt::Display for ResultRow
+{
+    fn fmt(
+        &self,
+        f : &mut fmt::Formatter
+    ) -> fmt::Result
+    {
+        write!(
+            f,
+            "{} {} {} {} {} {} {} {}",
+            self.name,
+            self.validators,
+            self.transactions,
+            self.credits,
+            self.timely_credits,
+            self.avg_latency,
+            self.normal_pct,
+            self.timely_pct
+        )?;
+        if let Some((stake, stake_share)) = self.stake {
+            write!(f, " {} {}", stake, stake_share)?;
+        }
+        Ok(())
+    }
+}
+
+// Reads records, one per line, skipping blank lines.  Errors name the source (e.g. a file path) and the line number.
+pub fn read<T : Record, R : BufRead>(
+    input : R,
+    source : &str
+) -> Records<T, R>
+{
+    Records { lines : input.lines(), source : source.to_string(), line_number : 0, record : std::marker::PhantomData }
+}
+
+// Opens a file written by fetch_data, such as vote data or slot metadata, decompressing it if it was compressed with
+// gzip (as the files in "data" and "data_slots" are), which is told by how it starts rather than by its name
+pub fn open(path : &Path) -> Result<Box<dyn BufRead + Send>, String>
+{
+    let file = std::fs::File::open(path).map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+
+    let mut input = BufReader::new(file);
+
+    let is_gzip = input
+        .fill_buf()
+        .map(|data| data.starts_with(GZIP_MAGIC))
+        .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+
+    if is_gzip {
+        Ok(Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(input))))
+    }
+    else {
+        Ok(Box::new(input))
+    }
+}
+
+pub struct Records<T, R>
+{
+    lines : std::io::Lines<R>,
+
+    source : String,
+
+    line_number : usize,
+
+    record : std::marker::PhantomData<T>
+}
+
+impl<T : Record, R : BufRead> Iterator for Records<T, R>
+{
+    type Item = Result<T, String>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        loop {
+            self.line_number += 1;
+            match self.lines.next()? {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => {
+                    return Some(
+                        T::parse(&line).map_err(|e| {
+                            format!("Invalid {} line {} ({}): {}", self.source, self.line_number, e, line)
+                        })
+                    )
+                },
+                Err(e) => return Some(Err(format!("Error reading {} line {}: {}", self.source, self.line_number, e)))
+            }
+        }
+    }
+}
+
+fn fields(line : &str) -> Vec<&str>
+{
+    line.split_whitespace().collect()
+}
+
+fn field<T : std::str::FromStr>(
+    what : &str,
+    value : &str
+) -> Result<T, String>
+where
+    T::Err : fmt::Display
+{
+    value.parse::<T>().map_err(|e| format!("{} {}: {}", what, value, e))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // Parses a line, writes it back out, and parses that again, which must give back the same line each time
+    fn round_trip<T : Record>(line : &str) -> T
+    {
+        let record = T::parse(line).unwrap();
+
+        assert_eq!(record.to_string(), line);
+
+        let reparsed = T::parse(&record.to_string()).unwrap();
+
+        assert_eq!(reparsed.to_string(), line);
+
+        reparsed
+    }
+
+    #[test]
+    fn vote_line_round_trip()
+    {
+        match round_trip::<VoteLine>("# 600 259200000 259631999") {
+            VoteLine::Header(header) => {
+                assert_eq!((header.epoch, header.first_slot, header.last_slot), (600, 259200000, 259631999))
+            },
+            _ => panic!("expected header")
+        }
+
+        match round_trip::<VoteLine>("259200005 Vote111111111111111111111111111111111111111 259200003 259200004") {
+            VoteLine::Landed { slot, vote_account, voted_slots } => {
+                assert_eq!(slot, 259200005);
+                assert_eq!(vote_account, "Vote111111111111111111111111111111111111111");
+                assert_eq!(voted_slots, vec![259200003, 259200004]);
+            },
+            _ => panic!("expected landed vote")
+        }
+
+        // A landed vote transaction may newly vote on no slots at all
+        match round_trip::<VoteLine>("259200005 Vote111111111111111111111111111111111111111") {
+            VoteLine::Landed { voted_slots, .. } => assert!(voted_slots.is_empty()),
+            _ => panic!("expected landed vote")
+        }
+
+        match round_trip::<VoteLine>("259200006 Vote111111111111111111111111111111111111111 !LockoutConflict") {
+            VoteLine::Failed { slot, error_kind, .. } => {
+                assert_eq!(slot, 259200006);
+                assert_eq!(error_kind, "LockoutConflict");
+            },
+            _ => panic!("expected failed vote")
+        }
+
+        assert!(VoteLine::parse("259200006 Vote111111111111111111111111111111111111111 !LockoutConflict 1").is_err());
+        assert!(VoteLine::parse("259200006").is_err());
+        assert!(VoteLine::parse("x Vote111111111111111111111111111111111111111").is_err());
+    }
+
+    #[test]
+    fn histogram_round_trip()
+    {
+        let latencies : Vec<String> = (0..LATENCIES).map(|latency| (latency * 3).to_string()).collect();
+
+        let line = format!("dc1 Vote111111111111111111111111111111111111111 1000 900 {}", latencies.join(" "));
+
+        let histogram = round_trip::<Histogram>(&line);
+
+        assert_eq!(histogram.data_center, "dc1");
+        assert_eq!(histogram.total_transactions, 1000);
+        assert_eq!(histogram.total_credits, 900);
+        assert_eq!(histogram.latencies.len(), LATENCIES);
+        assert_eq!(histogram.latencies[5], 15);
+        assert_eq!(histogram.stake, None);
+
+        let histogram = round_trip::<Histogram>(&format!("{} 5000000000", line));
+
+        assert_eq!(histogram.stake, Some(5000000000));
+
+        assert!(Histogram::parse("dc1 Vote111111111111111111111111111111111111111 1000 900 1 2 3").is_err());
+    }
+
+    #[test]
+    fn result_row_round_trip()
+    {
+        let row = round_trip::<ResultRow>("dc1 3 1000 900 14000 1.25 0.975 0.9125");
+
+        assert_eq!(row.name, "dc1");
+        assert_eq!((row.validators, row.transactions, row.credits, row.timely_credits), (3, 1000, 900, 14000));
+        assert_eq!((row.avg_latency, row.normal_pct, row.timely_pct), (1.25, 0.975, 0.9125));
+        assert!(row.stake.is_none());
+
+        let row = round_trip::<ResultRow>("dc1 3 1000 900 14000 1.25 0.975 0.9125 5000000000 0.25");
+
+        assert_eq!(row.stake, Some((5000000000, 0.25)));
+
+        assert!(ResultRow::parse("dc1 3 1000 900 14000 1.25 0.975 0.9125 5000000000").is_err());
+    }
+
+    #[test]
+    fn errors_give_line_number_counting_blank_lines()
+    {
+        let input = "# 600 0 431999\n\n5 Vote111111111111111111111111111111111111111 3\n\n\n6 Vote11 x\n";
+
+        let results : Vec<Result<VoteLine, String>> = read(input.as_bytes(), "votes").collect();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+
+        let e = results[2].as_ref().err().unwrap();
+
+        assert!(e.starts_with("Invalid votes line 6 "), "{}", e);
+    }
+}
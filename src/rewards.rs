@@ -0,0 +1,155 @@
+// Model of epoch staking rewards.  Each epoch, a pool of rewards is split between vote accounts in proportion to their
+// points, which are stake * vote credits earned in the epoch, and each vote account's rewards are then split between
+// the validator (its commission) and its stakers.  A reward pool is selected on the command line with an argument of
+// the form:
+
+// pool:LAMPORTS[:EPOCHS_PER_YEAR]                 -- A fixed pool of LAMPORTS per epoch
+// inflation:RATE:SUPPLY[:EPOCHS_PER_YEAR]         -- A pool of the fraction RATE of SUPPLY lamports per year, i.e. the
+//                                                   validator inflation rate and total supply (capitalization)
+
+// EPOCHS_PER_YEAR is used for the inflation pool and for annualizing staker returns, and defaults to the number of
+// 432,000 slot epochs in a year of 400 ms slots.
+
+use std::collections::HashMap;
+
+// Slots per year at 160 ticks per second and 64 ticks per slot, divided by the mainnet slots per epoch
+const DEFAULT_EPOCHS_PER_YEAR : f64 = (365.242_199 * 24_f64 * 60_f64 * 60_f64 * (160_f64 / 64_f64)) / 432_000_f64;
+
+pub struct RewardPool
+{
+    // Lamports of rewards per epoch
+    pub lamports : u64,
+
+    pub epochs_per_year : f64
+}
+
+impl std::str::FromStr for RewardPool
+{
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err>
+    {
+        let split : Vec<&str> = s.split(':').collect();
+
+        let parse =
+            |value : &str| value.parse::<f64>().map_err(|e| format!("Invalid reward pool {} ({}): {}", s, e, value));
+
+        let (lamports, epochs_per_year) = match split.as_slice() {
+            ["pool", lamports] => (parse(lamports)?, DEFAULT_EPOCHS_PER_YEAR),
+            ["pool", lamports, epochs_per_year] => (parse(lamports)?, parse(epochs_per_year)?),
+            ["inflation", rate, supply] => {
+                ((parse(rate)? * parse(supply)?) / DEFAULT_EPOCHS_PER_YEAR, DEFAULT_EPOCHS_PER_YEAR)
+            },
+            ["inflation", rate, supply, epochs_per_year] => {
+                let epochs_per_year = parse(epochs_per_year)?;
+                ((parse(rate)? * parse(supply)?) / epochs_per_year, epochs_per_year)
+            },
+            _ => {
+                return Err(format!(
+                    "Invalid reward pool {} (must be pool:LAMPORTS[:EPOCHS_PER_YEAR] or \
+                     inflation:RATE:SUPPLY[:EPOCHS_PER_YEAR])",
+                    s
+                ))
+            },
+        };
+
+        if lamports < 0_f64 || epochs_per_year <= 0_f64 {
+            return Err(format!(
+                "Invalid reward pool {}: lamports must not be negative, and epochs per year must be positive",
+                s
+            ));
+        }
+
+        Ok(RewardPool { lamports : lamports as u64, epochs_per_year })
+    }
+}
+
+// One vote account's share of the reward pool
+#[derive(Clone, Copy, Default)]
+pub struct Rewards
+{
+    // Lamports paid to the validator as commission
+    pub validator : u64,
+
+    // Lamports paid to the stakers
+    pub stakers : u64
+}
+
+impl std::ops::AddAssign for Rewards
+{
+    fn add_assign(
+        &mut self,
+        other : Self
+    )
+    {
+        self.validator += other.validator;
+        self.stakers += other.stakers;
+    }
+}
+
+impl Rewards
+{
+    pub fn total(&self) -> u64
+    {
+        self.validator + self.stakers
+    }
+
+    // Annual percentage yield of the stakers of the given stake, compounding every epoch
+    pub fn staker_apy(
+        &self,
+        stake : u64,
+        epochs_per_year : f64
+    ) -> f64
+    {
+        if stake == 0 {
+            return 0_f64;
+        }
+
+        (1_f64 + ((self.stakers as f64) / (stake as f64))).powf(epochs_per_year) - 1_f64
+    }
+}
+
+// Splits the reward pool between vote accounts, each given as (stake, credits, commission), where commission is a
+// percentage.  Returns the rewards of each vote account, in the same order.
+pub fn split_rewards(
+    pool : &RewardPool,
+    vote_accounts : &[(u64, u64, u8)]
+) -> Vec<Rewards>
+{
+    let total_points =
+        vote_accounts.iter().map(|(stake, credits, _)| (*stake as u128) * (*credits as u128)).sum::<u128>();
+
+    vote_accounts
+        .iter()
+        .map(|(stake, credits, commission)| {
+            if total_points == 0 {
+                return Rewards::default();
+            }
+            let total = (((pool.lamports as u128) * (*stake as u128) * (*credits as u128)) / total_points) as u64;
+            let validator = (((total as u128) * (*commission as u128)) / 100) as u64;
+            Rewards { validator, stakers : total - validator }
+        })
+        .collect()
+}
+
+// Loads a commission file, whose lines are of the form:
+// VOTE_ID COMMISSION
+// where COMMISSION is a percentage
+pub fn load_commissions(path : &String) -> Result<HashMap<String, u8>, String>
+{
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("Error reading commissions from {}: {}", path, e))?;
+
+    contents
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once(' ') {
+            Some((vote_account, commission)) => match commission.parse::<u8>() {
+                Ok(commission) if commission <= 100 => Ok((vote_account.to_string(), commission)),
+                Ok(_) => Err(format!("Invalid commissions line in {} (more than 100%): {}", path, line)),
+                Err(e) => Err(format!("Invalid commissions line in {} ({}): {}", path, e, line))
+            },
+            None => Err(format!("Invalid commissions line in {}: {}", path, line))
+        })
+        .collect()
+}
@@ -0,0 +1,136 @@
+// Running the pipeline's programs from other programs: finding them, running them on some input and capturing their
+// output, and running many such jobs at once on a bounded number of worker threads.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Number of lines from the end of a failed program's stderr to report
+const STDERR_TAIL_LINES : usize = 5;
+
+// The path of one of the pipeline's programs, which are all built into the same directory as the running program
+pub fn program_path(name : &str) -> Result<PathBuf, String>
+{
+    std::env::current_exe()
+        .map(|program| program.with_file_name(name))
+        .map_err(|e| format!("Error finding the path of {}: {}", name, e))
+}
+
+// Runs a program with the given arguments, feeding it the given input (or nothing), and returns what it writes to
+// stdout.  If it fails, the error ends with the last few lines that it wrote to stderr.
+pub fn run(
+    program : &Path,
+    args : &[String],
+    input : Option<Box<dyn Read + Send>>
+) -> Result<String, String>
+{
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Error running {}: {}", program.display(), e))?;
+
+    let stdin = child.stdin.take();
+
+    // Feed input from another thread, so that a program that writes as it reads can't block on a full stdout
+    let output = std::thread::scope(|scope| {
+        let feeder = scope.spawn(move || match (input, stdin) {
+            (Some(mut input), Some(mut stdin)) => std::io::copy(&mut input, &mut stdin).map(|_| ()),
+            _ => Ok(())
+        });
+        let output = child.wait_with_output();
+        // Input that the program stopped reading early isn't an error of its own; the program's status says why
+        let _ = feeder.join();
+        output
+    })
+    .map_err(|e| format!("Error running {}: {}", program.display(), e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr : Vec<&str> = stderr.lines().filter(|line| !line.trim().is_empty()).collect();
+        return Err(format!(
+            "{} {} failed ({}): {}",
+            program.display(),
+            args.join(" "),
+            output.status,
+            stderr[stderr.len().saturating_sub(STDERR_TAIL_LINES)..].join(" / ")
+        ));
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| format!("Invalid output from {}: {}", program.display(), e))
+}
+
+// Writes a file by writing a temporary file beside it and renaming that into place, so that the file is never left
+// partially written
+pub fn write_file(
+    path : &Path,
+    contents : &[u8]
+) -> Result<(), String>
+{
+    let mut temporary = path.as_os_str().to_owned();
+
+    temporary.push(".tmp");
+
+    std::fs::write(&temporary, contents)
+        .and_then(|_| std::fs::rename(&temporary, path))
+        .map_err(|e| format!("Error writing {}: {}", path.display(), e))
+}
+
+// A unit of work for run_jobs
+pub struct Job
+{
+    // Describes the job in progress reports, e.g. "process epoch 600"
+    pub description : String,
+
+    pub work : Box<dyn FnOnce() -> Result<(), String> + Send>
+}
+
+// Runs jobs on at most the given number of worker threads at once, reporting to stderr as each finishes, and returns
+// the description and error of every job that failed
+pub fn run_jobs(
+    jobs : Vec<Job>,
+    workers : usize
+) -> Vec<(String, String)>
+{
+    let total = jobs.len();
+
+    let queue = Mutex::new(jobs.into_iter().collect::<VecDeque<Job>>());
+
+    // (number of jobs finished, failed jobs)
+    let progress = Mutex::new((0_usize, Vec::<(String, String)>::new()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, total.max(1)) {
+            scope.spawn(|| loop {
+                let Some(job) = queue.lock().unwrap().pop_front()
+                else {
+                    break;
+                };
+                let start = std::time::Instant::now();
+                let result = (job.work)();
+                let mut progress = progress.lock().unwrap();
+                progress.0 += 1;
+                match result {
+                    Ok(()) => {
+                        eprintln!(
+                            "[{}/{}] {} ({:.1}s)",
+                            progress.0,
+                            total,
+                            job.description,
+                            start.elapsed().as_secs_f64()
+                        )
+                    },
+                    Err(e) => {
+                        eprintln!("[{}/{}] {} FAILED: {}", progress.0, total, job.description, e);
+                        progress.1.push((job.description, e));
+                    }
+                }
+            });
+        }
+    });
+
+    progress.into_inner().unwrap().1
+}
@@ -0,0 +1,510 @@
+// A scenario describes a whole run of the pipeline in one TOML file: the clusters and epochs to use, which metadata to
+// use, how to process the fetched data, and which credit policies, aggregation levels, and output formats to compute
+// results for.  A scenario is validated in full when loaded, so that mistakes are reported before any work is done.
+// Everything a scenario produces is written under scenarios/NAME/CLUSTER, named after the scenario, its clusters and
+// its policies, rather than after raw parameters.  For example:
+//
+// name = "agave_tower"
+// levels = ["validators", "data_centers"]
+// formats = ["text", "csv"]
+//
+// [[clusters]]
+// name = "mainnet-beta"
+// validators = "validators_app_mainnet_beta.json"
+// first_epoch = 600
+//
+// [processing]
+// credit_mode = "tower"
+//
+// [[policies]]
+// name = "agave"
+// spec = "agave"
+//
+// [[policies]]
+// name = "gentle"
+// kind = "linear"
+// grace = 4
+// max_credits = 60
+// multiplier = 1.0
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario
+{
+    // Names the directory under "scenarios" that everything is written into
+    pub name : String,
+
+    // Which of "validators", "data_centers", and "stake_weighted_data_centers" to compute results for
+    #[serde(default = "default_levels")]
+    pub levels : Vec<String>,
+
+    // Which of "text" (space separated, as written by calculate_results) and "csv" to write results in
+    #[serde(default = "default_formats")]
+    pub formats : Vec<String>,
+
+    // What pcts are relative to: "best" (the best validator or data center) or "theoretical" (the most credits
+    // achievable in the epoch, which requires slot metadata)
+    #[serde(default = "default_normalize")]
+    pub normalize : String,
+
+    pub clusters : Vec<Cluster>,
+
+    #[serde(default)]
+    pub processing : Processing,
+
+    #[serde(default)]
+    pub metadata : Metadata,
+
+    pub policies : Vec<Policy>
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cluster
+{
+    pub name : String,
+
+    // Directory holding the cluster's fetched data, laid out as fetch_data.sh and fetch_stakes.sh write it: data,
+    // data_gaps, data_slots, and data_stakes
+    #[serde(default = "default_directory")]
+    pub directory : String,
+
+    // validators.app JSON file giving the data center of each validator, if any
+    pub validators : Option<String>,
+
+    // Range of epochs to use (inclusive), defaulting to all fetched epochs
+    pub first_epoch : Option<u64>,
+
+    pub last_epoch : Option<u64>
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Processing
+{
+    // See process_data
+    #[serde(default = "default_min_coverage")]
+    pub min_coverage : f64,
+
+    #[serde(default = "default_latency_origin")]
+    pub latency_origin : String,
+
+    #[serde(default = "default_credit_mode")]
+    pub credit_mode : String,
+
+    // If given, epochs are also split into blocks of this many slots, for bootstrap resampling
+    pub block_slots : Option<u64>
+}
+
+// Which of the metadata sources to use for epochs that have them
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metadata
+{
+    #[serde(default = "default_true")]
+    pub gaps : bool,
+
+    #[serde(default = "default_true")]
+    pub slots : bool,
+
+    #[serde(default = "default_true")]
+    pub stakes : bool
+}
+
+// A credit policy, given either as a spec of the form that calculate_results takes (see credit_policy.rs), or as a
+// kind ("linear", "exponential", "step", "table", or "agave") with the parameters of that kind
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy
+{
+    // Names the directory that the policy's results are written into
+    pub name : String,
+
+    pub spec : Option<String>,
+
+    pub kind : Option<String>,
+
+    pub grace : Option<u64>,
+
+    pub max_credits : Option<u64>,
+
+    pub multiplier : Option<f64>,
+
+    pub decay : Option<f64>,
+
+    // (latency, credits) pairs
+    pub steps : Option<Vec<(u64, u64)>>,
+
+    pub table : Option<String>
+}
+
+fn default_levels() -> Vec<String>
+{
+    vec!["validators".to_string(), "data_centers".to_string()]
+}
+
+fn default_formats() -> Vec<String>
+{
+    vec!["text".to_string()]
+}
+
+fn default_normalize() -> String
+{
+    "best".to_string()
+}
+
+fn default_directory() -> String
+{
+    ".".to_string()
+}
+
+fn default_min_coverage() -> f64
+{
+    0.99
+}
+
+fn default_latency_origin() -> String
+{
+    "landing".to_string()
+}
+
+fn default_credit_mode() -> String
+{
+    "landed".to_string()
+}
+
+fn default_true() -> bool
+{
+    true
+}
+
+impl Default for Processing
+{
+    fn default() -> Self
+    {
+        Processing {
+            min_coverage : default_min_coverage(),
+            latency_origin : default_latency_origin(),
+            credit_mode : default_credit_mode(),
+            block_slots : None
+        }
+    }
+}
+
+impl Default for Metadata
+{
+    fn default() -> Self
+    {
+        Metadata { gaps : true, slots : true, stakes : true }
+    }
+}
+
+// Loads and validates a scenario
+pub fn load(path : &String) -> Result<Scenario, String>
+{
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Error reading scenario {}: {}", path, e))?;
+
+    let scenario : Scenario = toml::from_str(&contents).map_err(|e| format!("Invalid scenario {}: {}", path, e))?;
+
+    scenario.validate().map_err(|e| format!("Invalid scenario {}: {}", path, e))?;
+
+    Ok(scenario)
+}
+
+impl Scenario
+{
+    fn validate(&self) -> Result<(), String>
+    {
+        check_name("scenario", &self.name)?;
+
+        check_unique("cluster", self.clusters.iter().map(|c| &c.name))?;
+
+        for cluster in &self.clusters {
+            check_name("cluster", &cluster.name)?;
+            if let Some(validators) = &cluster.validators {
+                if !Path::new(validators).is_file() {
+                    return Err(format!("cluster {} validators file {} does not exist", cluster.name, validators));
+                }
+            }
+            if let (Some(first_epoch), Some(last_epoch)) = (cluster.first_epoch, cluster.last_epoch) {
+                if first_epoch > last_epoch {
+                    return Err(format!(
+                        "cluster {} first_epoch {} is after last_epoch {}",
+                        cluster.name, first_epoch, last_epoch
+                    ));
+                }
+            }
+        }
+
+        if !(0_f64..=1_f64).contains(&self.processing.min_coverage) {
+            return Err(format!("min_coverage {} must be from 0 to 1", self.processing.min_coverage));
+        }
+
+        check_choice("latency_origin", &self.processing.latency_origin, &["landing", "parent", "block"])?;
+
+        if self.processing.latency_origin != "landing" && !self.metadata.slots {
+            return Err(format!("latency_origin {} requires slots metadata", self.processing.latency_origin));
+        }
+
+        check_choice("credit_mode", &self.processing.credit_mode, &["landed", "tower"])?;
+
+        if self.processing.block_slots == Some(0) {
+            return Err("block_slots must be at least 1".to_string());
+        }
+
+        check_unique("policy", self.policies.iter().map(|p| &p.name))?;
+
+        for policy in &self.policies {
+            check_name("policy", &policy.name)?;
+            policy.spec()?;
+        }
+
+        check_unique("level", self.levels.iter())?;
+
+        for level in &self.levels {
+            check_choice("level", level, &["validators", "data_centers", "stake_weighted_data_centers"])?;
+        }
+
+        if self.levels.iter().any(|level| level == "stake_weighted_data_centers") && !self.metadata.stakes {
+            return Err("level stake_weighted_data_centers requires stakes metadata".to_string());
+        }
+
+        check_unique("format", self.formats.iter())?;
+
+        for format in &self.formats {
+            check_choice("format", format, &["text", "csv"])?;
+        }
+
+        check_choice("normalize", &self.normalize, &["best", "theoretical"])?;
+
+        if self.normalize == "theoretical" && !self.metadata.slots {
+            return Err("normalize theoretical requires slots metadata".to_string());
+        }
+
+        Ok(())
+    }
+
+    // The directory that everything the scenario produces for a cluster is written under
+    pub fn directory(
+        &self,
+        cluster : &Cluster
+    ) -> PathBuf
+    {
+        Path::new("scenarios").join(&self.name).join(&cluster.name)
+    }
+
+    // The path of one of a cluster's metadata files for an epoch (e.g. "data_stakes"), if the scenario uses that kind of
+    // metadata and the epoch has it
+    pub fn metadata_path(
+        &self,
+        cluster : &Cluster,
+        kind : &str,
+        epoch : u64
+    ) -> Option<String>
+    {
+        let (used, file_name) = match kind {
+            "data_gaps" => (self.metadata.gaps, epoch.to_string()),
+            "data_slots" => (self.metadata.slots, format!("{}.gz", epoch)),
+            "data_stakes" => (self.metadata.stakes, epoch.to_string()),
+            _ => (false, String::new())
+        };
+
+        let path = Path::new(&cluster.directory).join(kind).join(file_name);
+
+        if used && path.is_file() {
+            Some(path.to_string_lossy().to_string())
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl Cluster
+{
+    // Whether an epoch is in the cluster's range of epochs
+    pub fn includes(
+        &self,
+        epoch : u64
+    ) -> bool
+    {
+        self.first_epoch.is_none_or(|first_epoch| epoch >= first_epoch) &&
+            self.last_epoch.is_none_or(|last_epoch| epoch <= last_epoch)
+    }
+}
+
+impl Policy
+{
+    // Returns the credit policy spec, after checking that it is a valid policy
+    pub fn spec(&self) -> Result<String, String>
+    {
+        // grace 0 would leave no latency earning max credits, which the policies can't represent
+        if self.grace == Some(0) {
+            return Err(format!("policy {} grace must be at least 1", self.name));
+        }
+
+        let spec = match (&self.spec, &self.kind) {
+            (Some(spec), None) => {
+                if self.grace.is_some() ||
+                    self.max_credits.is_some() ||
+                    self.multiplier.is_some() ||
+                    self.decay.is_some() ||
+                    self.steps.is_some() ||
+                    self.table.is_some()
+                {
+                    return Err(format!("policy {} gives spec, so must not give parameters", self.name));
+                }
+                spec.clone()
+            },
+            (None, Some(kind)) => self.spec_of_kind(kind)?,
+            _ => return Err(format!("policy {} must give exactly one of spec and kind", self.name))
+        };
+
+        let policy = crate::credit_policy::parse(&spec).map_err(|e| format!("policy {}: {}", self.name, e))?;
+
+        // Every landed vote earns at least 1 credit, at any of the 64 latencies that process_data counts
+        if let Some(latency) = (0..64).find(|latency| policy.credits(*latency) == 0) {
+            return Err(format!("policy {} gives no credits at latency {}", self.name, latency));
+        }
+
+        Ok(spec)
+    }
+
+    fn spec_of_kind(
+        &self,
+        kind : &str
+    ) -> Result<String, String>
+    {
+        let require = |what : &str, value : Option<String>| {
+            value.ok_or(format!("policy {} of kind {} requires {}", self.name, kind, what))
+        };
+
+        // The parameters that each kind takes; any others given are a mistake
+        let (spec, allowed) : (String, &[&str]) = match kind {
+            "linear" => (
+                format!(
+                    "linear:{}:{}:{}",
+                    require("grace", self.grace.map(|v| v.to_string()))?,
+                    require("max_credits", self.max_credits.map(|v| v.to_string()))?,
+                    require("multiplier", self.multiplier.map(|v| v.to_string()))?
+                ),
+                &["grace", "max_credits", "multiplier"]
+            ),
+            "exponential" => (
+                format!(
+                    "exponential:{}:{}:{}",
+                    require("grace", self.grace.map(|v| v.to_string()))?,
+                    require("max_credits", self.max_credits.map(|v| v.to_string()))?,
+                    require("decay", self.decay.map(|v| v.to_string()))?
+                ),
+                &["grace", "max_credits", "decay"]
+            ),
+            "step" => (
+                format!(
+                    "step:{}",
+                    require(
+                        "steps",
+                        self.steps.as_ref().map(|steps| {
+                            steps
+                                .iter()
+                                .map(|(latency, credits)| format!("{}={}", latency, credits))
+                                .collect::<Vec<String>>()
+                                .join(",")
+                        })
+                    )?
+                ),
+                &["steps"]
+            ),
+            "table" => (format!("table:{}", require("table", self.table.clone())?), &["table"]),
+            "agave" => ("agave".to_string(), &[]),
+            _ => {
+                return Err(format!(
+                    "policy {} kind {} must be \"linear\", \"exponential\", \"step\", \"table\", or \"agave\"",
+                    self.name, kind
+                ))
+            },
+        };
+
+        let given = [
+            ("grace", self.grace.is_some()),
+            ("max_credits", self.max_credits.is_some()),
+            ("multiplier", self.multiplier.is_some()),
+            ("decay", self.decay.is_some()),
+            ("steps", self.steps.is_some()),
+            ("table", self.table.is_some())
+        ];
+
+        if let Some((parameter, _)) = given.iter().find(|(parameter, given)| *given && !allowed.contains(parameter)) {
+            return Err(format!("policy {} of kind {} does not take {}", self.name, kind, parameter));
+        }
+
+        Ok(spec)
+    }
+}
+
+// Names become directory names, so are limited to characters that are safe in them
+fn check_name(
+    what : &str,
+    name : &str
+) -> Result<(), String>
+{
+    if name.is_empty() ||
+        name.starts_with('.') ||
+        !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+    {
+        return Err(format!("{} name \"{}\" must be letters, digits, _, -, and . (not first)", what, name));
+    }
+
+    Ok(())
+}
+
+fn check_unique<'a>(
+    what : &str,
+    names : impl Iterator<Item = &'a String>
+) -> Result<(), String>
+{
+    let mut seen = HashSet::new();
+
+    let mut any = false;
+
+    for name in names {
+        any = true;
+        if !seen.insert(name) {
+            return Err(format!("{} {} is given more than once", what, name));
+        }
+    }
+
+    if !any {
+        return Err(format!("at least one {} must be given", what));
+    }
+
+    Ok(())
+}
+
+fn check_choice(
+    what : &str,
+    value : &str,
+    choices : &[&str]
+) -> Result<(), String>
+{
+    if !choices.contains(&value) {
+        return Err(format!("{} {} must be one of {}", what, value, choices.join(", ")));
+    }
+
+    Ok(())
+}
+
+// Runs this same program with the given arguments, feeding it the given input (or nothing), and returns what it writes
+// to stdout.  This is how a scenario drives the steps that the scripts otherwise run one by one.
+pub fn run_self(
+    args : &[String],
+    input : Option<Box<dyn std::io::Read + Send>>
+) -> Result<String, String>
+{
+    let program = std::env::current_exe().map_err(|e| format!("Error finding this program: {}", e))?;
+
+    crate::runner::run(&program, args, input)
+}
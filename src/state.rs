@@ -0,0 +1,147 @@
+// Persistent state of fetch_data: the last landed slot of every vote account, which is carried from one epoch's fetch
+// to the next, and checkpoints from which an interrupted fetch can be resumed.
+
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Map from vote account to the last slot that it landed a vote on
+pub type VoteAccountState = HashMap<Pubkey, u64>;
+
+// Loads vote account state from a file of lines of the form:
+// VOTE_ACCOUNT LAST_LANDED_SLOT
+// A nonexistent file is an empty state.
+pub fn load_vote_account_state(path : &str) -> Result<VoteAccountState, String>
+{
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse_vote_account_state(path, contents.lines()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(format!("Error reading vote account state from {}: {}", path, e))
+    }
+}
+
+// Writes vote account state in the form read by load_vote_account_state
+pub fn write_vote_account_state(
+    path : &str,
+    vote_account_state : &VoteAccountState
+) -> Result<(), String>
+{
+    std::fs::write(path, format_vote_account_state(vote_account_state))
+        .map_err(|e| format!("Error writing vote account state to {}: {}", path, e))
+}
+
+fn parse_vote_account_state<'a>(
+    path : &str,
+    lines : impl Iterator<Item = &'a str>
+) -> Result<VoteAccountState, String>
+{
+    lines
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let split : Vec<&str> = line.split(' ').collect();
+            if split.len() != 2 {
+                return Err(format!("Invalid vote account state line in {}: {}", path, line));
+            }
+            let vote_account = Pubkey::from_str(split[0])
+                .map_err(|e| format!("Invalid vote account in {} ({}): {}", path, e, line))?;
+            let last_slot =
+                split[1].parse::<u64>().map_err(|e| format!("Invalid slot in {} ({}): {}", path, e, line))?;
+            Ok((vote_account, last_slot))
+        })
+        .collect()
+}
+
+fn format_vote_account_state(vote_account_state : &VoteAccountState) -> String
+{
+    let mut contents = String::new();
+
+    for (vote_account, last_slot) in vote_account_state {
+        contents.push_str(format!("{} {}\n", vote_account, last_slot).as_str());
+    }
+
+    contents
+}
+
+// The progress of a fetch into an output file.  A checkpoint is stored in a file of the form:
+// OUTPUT_LENGTH SLOTS_LENGTH GAPS_LENGTH NEXT_SLOT
+// followed by the vote account state in the form read by load_vote_account_state.
+pub struct Checkpoint
+{
+    // Number of bytes of the output file that were written before the checkpoint was taken; anything after this was
+    // written for slots at or after next_slot and will be written again on resume
+    pub output_length : u64,
+
+    // Same as output_length, for the slot metadata file (0 if there is none)
+    pub slots_length : u64,
+
+    // Same as output_length, for the gaps file (0 if there is none)
+    pub gaps_length : u64,
+
+    // The first slot that had not been completely fetched when the checkpoint was taken
+    pub next_slot : u64,
+
+    pub vote_account_state : VoteAccountState
+}
+
+impl Checkpoint
+{
+    // Loads a checkpoint, returning None if there is no checkpoint file
+    pub fn load(path : &str) -> Result<Option<Self>, String>
+    {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Error reading checkpoint from {}: {}", path, e))
+        };
+
+        let mut lines = contents.lines();
+
+        let split : Vec<&str> = lines.next().unwrap_or("").split(' ').collect();
+
+        if split.len() != 4 {
+            return Err(format!("Invalid checkpoint in {}", path));
+        }
+
+        let output_length =
+            split[0].parse::<u64>().map_err(|e| format!("Invalid output length in checkpoint {}: {}", path, e))?;
+
+        let slots_length =
+            split[1].parse::<u64>().map_err(|e| format!("Invalid slots length in checkpoint {}: {}", path, e))?;
+
+        let gaps_length =
+            split[2].parse::<u64>().map_err(|e| format!("Invalid gaps length in checkpoint {}: {}", path, e))?;
+
+        let next_slot = split[3].parse::<u64>().map_err(|e| format!("Invalid slot in checkpoint {}: {}", path, e))?;
+
+        Ok(Some(Checkpoint {
+            output_length,
+            slots_length,
+            gaps_length,
+            next_slot,
+            vote_account_state : parse_vote_account_state(path, lines)?
+        }))
+    }
+
+    // Writes the checkpoint to a temporary file which is then renamed into place, so that an interruption while
+    // writing never leaves a partial checkpoint behind
+    pub fn write(
+        &self,
+        path : &str
+    ) -> Result<(), String>
+    {
+        let tmp_path = format!("{}.tmp", path);
+
+        let contents = format!(
+            "{} {} {} {}\n{}",
+            self.output_length,
+            self.slots_length,
+            self.gaps_length,
+            self.next_slot,
+            format_vote_account_state(&self.vote_account_state)
+        );
+
+        std::fs::write(&tmp_path, contents)
+            .and_then(|_| std::fs::rename(&tmp_path, path))
+            .map_err(|e| format!("Error writing checkpoint to {}: {}", path, e))
+    }
+}
@@ -0,0 +1,237 @@
+// Statistics for analyzing how results vary: a small random number generator for resampling, rank correlations, and
+// percentile intervals.
+
+// SplitMix64 pseudo-random number generator, which is plenty for resampling and gives repeatable results from a seed
+pub struct Rng
+{
+    state : u64
+}
+
+impl Rng
+{
+    pub fn new(seed : u64) -> Self
+    {
+        Rng { state : seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64
+    {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Returns a number from 0 to n - 1 (inclusive)
+    pub fn below(
+        &mut self,
+        n : usize
+    ) -> usize
+    {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+// Kendall's tau-b rank correlation between two sets of values, paired by index, which accounts for ties.  1 means the
+// values are in the same order, -1 in the opposite order.  This compares every pair, so takes time proportional to the
+// square of the number of values.
+pub fn kendall_tau(
+    a : &[f64],
+    b : &[f64]
+) -> f64
+{
+    let mut concordant = 0_i64;
+
+    let mut discordant = 0_i64;
+
+    let mut tied_a = 0_i64;
+
+    let mut tied_b = 0_i64;
+
+    for i in 0..a.len() {
+        for j in (i + 1)..a.len() {
+            let order_a = a[i].partial_cmp(&a[j]).unwrap();
+            let order_b = b[i].partial_cmp(&b[j]).unwrap();
+            match (order_a.is_eq(), order_b.is_eq()) {
+                (true, true) => (),
+                (true, false) => tied_a += 1,
+                (false, true) => tied_b += 1,
+                (false, false) if order_a == order_b => concordant += 1,
+                (false, false) => discordant += 1
+            }
+        }
+    }
+
+    let denominator =
+        (((concordant + discordant + tied_a) as f64) * ((concordant + discordant + tied_b) as f64)).sqrt();
+
+    if denominator == 0_f64 {
+        return 0_f64;
+    }
+
+    ((concordant - discordant) as f64) / denominator
+}
+
+// Spearman's rank correlation between two sets of values, paired by index: the Pearson correlation of their ranks,
+// with tied values given the mean of their ranks
+pub fn spearman_rho(
+    a : &[f64],
+    b : &[f64]
+) -> f64
+{
+    pearson(&ranks(a), &ranks(b))
+}
+
+// Returns the rank of each value, from 1 for the smallest, with tied values given the mean of their ranks
+fn ranks(values : &[f64]) -> Vec<f64>
+{
+    let mut order : Vec<usize> = (0..values.len()).collect();
+
+    order.sort_by(|i, j| values[*i].partial_cmp(&values[*j]).unwrap());
+
+    let mut ranks = vec![0_f64; values.len()];
+
+    let mut start = 0;
+
+    while start < order.len() {
+        let mut end = start + 1;
+        while (end < order.len()) && (values[order[end]] == values[order[start]]) {
+            end += 1;
+        }
+        // Positions start to end - 1 are ranks start + 1 to end
+        let rank = ((start + 1 + end) as f64) / 2_f64;
+        for i in &order[start..end] {
+            ranks[*i] = rank;
+        }
+        start = end;
+    }
+
+    ranks
+}
+
+fn pearson(
+    a : &[f64],
+    b : &[f64]
+) -> f64
+{
+    let n = a.len() as f64;
+
+    let mean_a = a.iter().sum::<f64>() / n;
+
+    let mean_b = b.iter().sum::<f64>() / n;
+
+    let covariance = a.iter().zip(b).map(|(a, b)| (a - mean_a) * (b - mean_b)).sum::<f64>();
+
+    let variance_a = a.iter().map(|a| (a - mean_a).powi(2)).sum::<f64>();
+
+    let variance_b = b.iter().map(|b| (b - mean_b).powi(2)).sum::<f64>();
+
+    if variance_a == 0_f64 || variance_b == 0_f64 {
+        return 0_f64;
+    }
+
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+// Returns the interval between the given lower and upper percentiles (e.g. 2.5 and 97.5) of the values
+pub fn percentile_interval(
+    values : &mut [f64],
+    lower : f64,
+    upper : f64
+) -> (f64, f64)
+{
+    if values.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let at = |percentile : f64| values[(((values.len() - 1) as f64) * (percentile / 100_f64)).round() as usize];
+
+    (at(lower), at(upper))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_close(
+        actual : f64,
+        expected : f64
+    )
+    {
+        assert!((actual - expected).abs() < 1e-12, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn rng_is_repeatable_and_in_range()
+    {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+
+        let from_a : Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let from_b : Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        let from_c : Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+
+        assert_eq!(from_a, from_b);
+        assert_ne!(from_a, from_c);
+
+        // Resampling 7 values draws every index, and nothing out of range
+        let mut drawn = [false; 7];
+        for _ in 0..1000 {
+            drawn[a.below(7)] = true;
+        }
+        assert_eq!(drawn, [true; 7]);
+
+        assert_eq!(a.below(1), 0);
+    }
+
+    #[test]
+    fn kendall_tau_of_hand_computed_cases()
+    {
+        assert_close(kendall_tau(&[1_f64, 2_f64, 3_f64], &[10_f64, 20_f64, 30_f64]), 1_f64);
+        assert_close(kendall_tau(&[1_f64, 2_f64, 3_f64], &[3_f64, 2_f64, 1_f64]), -1_f64);
+
+        // 5 of 6 pairs concordant, 1 discordant
+        assert_close(kendall_tau(&[1_f64, 2_f64, 3_f64, 4_f64], &[1_f64, 3_f64, 2_f64, 4_f64]), 4_f64 / 6_f64);
+
+        // 2 concordant pairs and 1 tied in a only: 2 / sqrt(3 * 2)
+        assert_close(kendall_tau(&[1_f64, 2_f64, 2_f64], &[1_f64, 2_f64, 3_f64]), 2_f64 / 6_f64.sqrt());
+
+        // Constant values have no order to correlate
+        assert_close(kendall_tau(&[1_f64, 1_f64], &[1_f64, 2_f64]), 0_f64);
+    }
+
+    #[test]
+    fn spearman_rho_of_hand_computed_cases()
+    {
+        // Only the order matters
+        assert_close(spearman_rho(&[1_f64, 2_f64, 3_f64], &[1_f64, 10_f64, 100_f64]), 1_f64);
+        assert_close(spearman_rho(&[1_f64, 2_f64, 3_f64], &[5_f64, 4_f64, 0_f64]), -1_f64);
+
+        // 1 - 6 * (sum of squared rank differences of 2) / (4 * (16 - 1))
+        assert_close(spearman_rho(&[1_f64, 2_f64, 3_f64, 4_f64], &[1_f64, 3_f64, 2_f64, 4_f64]), 0.8);
+    }
+
+    #[test]
+    fn ties_share_the_mean_rank()
+    {
+        assert_eq!(ranks(&[10_f64, 20_f64, 20_f64, 5_f64]), vec![2_f64, 3.5, 3.5, 1_f64]);
+    }
+
+    #[test]
+    fn percentile_interval_of_hand_computed_cases()
+    {
+        // 0 through 100, out of order
+        let mut values : Vec<f64> = (0..=100).rev().map(|value| value as f64).collect();
+
+        assert_eq!(percentile_interval(&mut values, 2.5, 97.5), (3_f64, 98_f64));
+        assert_eq!(percentile_interval(&mut values, 0_f64, 100_f64), (0_f64, 100_f64));
+
+        let (lower, upper) = percentile_interval(&mut [], 2.5, 97.5);
+        assert!(lower.is_nan() && upper.is_nan());
+    }
+}
@@ -0,0 +1,208 @@
+// Simulation of a validator's vote tower, as kept in its vote account by the vote program.  A vote credit is only
+// earned when a voted slot is rooted, i.e. popped off the bottom of the full 31-deep tower; voted slots that are
+// popped off the top of the tower by lockout expiry never earn a credit.  A tower is carried from one epoch to the
+// next in a line of the form:
+// ROOT_SLOT SLOT:CONFIRMATION_COUNT:LATENCY...
+// where ROOT_SLOT is _ for none, and the votes run from the bottom of the tower to the top.
+
+use solana_vote_program::vote_state::{LandedVote, Lockout, VoteState, MAX_LOCKOUT_HISTORY};
+
+pub struct Tower
+{
+    vote_state : VoteState
+}
+
+impl Tower
+{
+    pub fn new() -> Self
+    {
+        Tower { vote_state : VoteState::default() }
+    }
+
+    // Applies a vote for voted_slot that landed with the given latency, exactly as the vote program applies each slot
+    // of a vote.  Votes for slots no newer than the last voted slot are ignored, as they are by the vote program.
+    // If applying the vote rooted a slot, returns the latency that was recorded for that slot when its vote landed.
+    pub fn vote(
+        &mut self,
+        epoch : u64,
+        voted_slot : u64,
+        latency : u64
+    ) -> Option<u64>
+    {
+        if self.vote_state.last_voted_slot().is_some_and(|last_voted_slot| voted_slot <= last_voted_slot) {
+            return None;
+        }
+
+        // A slot is only rooted if the tower is still full after expired lockouts are popped, in which case the
+        // rooted slot is the one at the bottom of the tower
+        let bottom = self.vote_state.votes.front().cloned();
+
+        let root_slot = self.vote_state.root_slot;
+
+        self.vote_state.process_next_vote_slot(voted_slot, epoch);
+
+        // Record the landing latency of the new vote, saturated to what the vote account can hold
+        if let Some(landed_vote) = self.vote_state.votes.back_mut() {
+            landed_vote.latency = latency.min(u8::MAX as u64) as u8;
+        }
+
+        match bottom {
+            Some(bottom) if self.vote_state.root_slot != root_slot => {
+                debug_assert!(self.vote_state.votes.len() == MAX_LOCKOUT_HISTORY);
+                Some(bottom.latency as u64)
+            },
+            _ => None
+        }
+    }
+}
+
+impl std::fmt::Display for Tower
+{
+    fn fmt(
+        &self,
+        f : &mut std::fmt::Formatter
+    ) -> std::fmt::Result
+    {
+        match self.vote_state.root_slot {
+            Some(root_slot) => write!(f, "{}", root_slot)?,
+            None => write!(f, "_")?
+        }
+
+        for vote in &self.vote_state.votes {
+            write!(f, " {}:{}:{}", vote.slot(), vote.lockout.confirmation_count(), vote.latency)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Tower
+{
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err>
+    {
+        let mut split = s.split_whitespace();
+
+        let mut vote_state = VoteState::default();
+
+        vote_state.root_slot = match split.next() {
+            Some("_") => None,
+            Some(root_slot) => Some(root_slot.parse::<u64>().map_err(|e| format!("root slot {}: {}", root_slot, e))?),
+            None => return Err("expected ROOT_SLOT".to_string())
+        };
+
+        for vote in split {
+            let fields : Vec<&str> = vote.split(':').collect();
+            let [slot, confirmation_count, latency] = fields[..]
+            else {
+                return Err(format!("vote {} is not SLOT:CONFIRMATION_COUNT:LATENCY", vote));
+            };
+            let slot = slot.parse::<u64>().map_err(|e| format!("vote slot {}: {}", slot, e))?;
+            let confirmation_count = confirmation_count
+                .parse::<u32>()
+                .ok()
+                .filter(|count| (1..=(MAX_LOCKOUT_HISTORY as u32)).contains(count))
+                .ok_or(format!("vote confirmation count {}", confirmation_count))?;
+            let latency = latency.parse::<u8>().map_err(|e| format!("vote latency {}: {}", latency, e))?;
+            if vote_state.last_voted_slot().or(vote_state.root_slot).is_some_and(|last_slot| slot <= last_slot) {
+                return Err(format!("vote slot {} is not after the slots below it", slot));
+            }
+            vote_state.votes.push_back(LandedVote {
+                latency,
+                lockout : Lockout::new_with_confirmation_count(slot, confirmation_count)
+            });
+        }
+
+        if vote_state.votes.len() > MAX_LOCKOUT_HISTORY {
+            return Err(format!("more than {} votes", MAX_LOCKOUT_HISTORY));
+        }
+
+        Ok(Tower { vote_state })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn slots_are_rooted_at_depth_31()
+    {
+        let mut tower = Tower::new();
+
+        // The tower fills without rooting anything
+        for slot in 1..=31 {
+            assert_eq!(tower.vote(600, slot, slot % 5), None);
+        }
+
+        // Each further vote roots the slot at the bottom, with the latency that its vote landed with
+        assert_eq!(tower.vote(600, 32, 0), Some(1));
+        assert_eq!(tower.vote(600, 33, 0), Some(2));
+        assert_eq!(tower.vote(600, 34, 0), Some(3));
+    }
+
+    #[test]
+    fn slots_popped_by_lockout_expiry_are_never_rooted()
+    {
+        let mut tower = Tower::new();
+
+        // Slot 1's lockout of 2 slots has expired by slot 10, so the vote for 10 pops it off the tower
+        assert_eq!(tower.vote(600, 1, 7), None);
+        assert_eq!(tower.vote(600, 10, 3), None);
+
+        for slot in 11..=40 {
+            assert_eq!(tower.vote(600, slot, 0), None);
+        }
+
+        // The first slot rooted is 10, at its own latency
+        assert_eq!(tower.vote(600, 41, 0), Some(3));
+    }
+
+    #[test]
+    fn latency_is_recorded_when_the_vote_lands()
+    {
+        let mut tower = Tower::new();
+
+        // Saturated to what the vote account can hold
+        assert_eq!(tower.vote(600, 1, 1000), None);
+
+        // Votes for slots no newer than the last voted slot change nothing
+        assert_eq!(tower.vote(600, 1, 0), None);
+        assert_eq!(tower.vote(600, 0, 0), None);
+
+        for slot in 2..=31 {
+            assert_eq!(tower.vote(600, slot, 0), None);
+        }
+
+        assert_eq!(tower.vote(601, 32, 0), Some(u8::MAX as u64));
+    }
+
+    #[test]
+    fn towers_are_carried_in_text()
+    {
+        assert_eq!(Tower::new().to_string(), "_");
+
+        let mut tower = Tower::new();
+
+        for slot in 1..=33 {
+            tower.vote(600, slot, slot % 3);
+        }
+
+        let carried = tower.to_string().parse::<Tower>().unwrap();
+
+        assert_eq!(carried.to_string(), tower.to_string());
+        assert!(carried.to_string().starts_with("2 3:31:0 4:30:1 "));
+
+        // The carried tower goes on rooting where the original left off
+        for mut tower in [tower, carried] {
+            assert_eq!(tower.vote(601, 34, 0), Some(0));
+            assert_eq!(tower.vote(601, 35, 0), Some(1));
+        }
+
+        for invalid in ["", "x", "_ 5", "_ 5:1", "_ 5:0:0", "_ 5:1:256", "_ 5:2:0 5:1:0", "10 5:1:0"] {
+            assert!(invalid.parse::<Tower>().is_err(), "{} was accepted", invalid);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// Details of validators from a validators.app JSON file (e.g. validators_app_mainnet_beta.json, as written by
+// fetch_validators_app_mainnet_beta.sh), which is a list of objects with these fields among others.
+
+#[derive(serde::Deserialize)]
+pub struct ValidatorDetails
+{
+    pub vote_account : Option<String>,
+
+    pub name : Option<String>,
+
+    pub avatar_url : Option<String>,
+
+    pub data_center_key : Option<String>
+}
+
+pub fn load_validator_details(path : &String) -> Result<Vec<ValidatorDetails>, String>
+{
+    load_json_file(path)
+}
+
+pub fn load_json_file<T : for<'de> serde::de::Deserialize<'de>>(path : &String) -> Result<T, String>
+{
+    let file =
+        std::fs::File::open(path).map_err(|e| format!("Error opening JSON file {} for read:\n    {}", path, e))?;
+
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("Error reading JSON from {}:\n    {}\n", path, e))
+}
@@ -0,0 +1,856 @@
+// A compact binary form of the vote data that fetch_data writes, which is much smaller and much faster to read than
+// the text form, since vote accounts are written as 32 bytes once each rather than in base58 on every line, and slots
+// are written as small differences rather than in decimal.  A vote stream is:
+//
+// MAGIC VERSION CLUSTER EPOCH FIRST_SLOT LAST_SLOT TOOL_VERSION RECORD...
+//
+// where MAGIC is the 8 bytes "VOTESTRM", VERSION is one byte (currently 1), CLUSTER and TOOL_VERSION are strings (a
+// varint length followed by that many bytes of UTF-8), and EPOCH, FIRST_SLOT, and LAST_SLOT are varints.  Varints are
+// unsigned LEB128: 7 bits at a time, least significant first, with the high bit set on every byte but the last.
+// Signed differences are zigzag encoded into varints (0, -1, 1, -2, ... become 0, 1, 2, 3, ...).  Each RECORD is a
+// tag byte followed by:
+//
+// 0 (vote account)  -- its 32 bytes; vote accounts are numbered from 0 in the order they are given
+// 1 (error kind)    -- a string; error kinds are numbered from 0 in the order they are given
+// 2 (landed)        -- SLOT_DIFFERENCE VOTE_ACCOUNT COUNT FIRST_VOTED_SLOT_DIFFERENCE VOTED_SLOT_DIFFERENCE...
+// 3 (failed)        -- SLOT_DIFFERENCE VOTE_ACCOUNT ERROR_KIND
+//
+// SLOT_DIFFERENCE is the signed difference of the vote transaction's slot from that of the previous landed or failed
+// record (or from FIRST_SLOT), VOTE_ACCOUNT and ERROR_KIND are the varint numbers of ones given earlier, COUNT is the
+// number of voted slots, FIRST_VOTED_SLOT_DIFFERENCE is the signed difference of the first voted slot below the
+// transaction's slot, and each VOTED_SLOT_DIFFERENCE is the signed difference of a voted slot from the one before it.
+// A vote account or error kind is always given just before the first record that uses it.
+
+use crate::records::{EpochHeader, VoteLine};
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+
+pub const MAGIC : &[u8; 8] = b"VOTESTRM";
+
+pub const VERSION : u8 = 1;
+
+const VOTE_ACCOUNT : u8 = 0;
+
+const ERROR_KIND : u8 = 1;
+
+const LANDED : u8 = 2;
+
+const FAILED : u8 = 3;
+
+pub struct StreamHeader
+{
+    // Name of the cluster the votes were fetched from, e.g. "mainnet-beta", or empty if not known
+    pub cluster : String,
+
+    pub epoch : u64,
+
+    pub first_slot : u64,
+
+    pub last_slot : u64,
+
+    // Version of the program that wrote the stream
+    pub tool_version : String
+}
+
+// One landed or failed vote transaction of a vote stream, whose vote account and error kind are numbers that the
+// Reader gives the values of
+pub enum Record
+{
+    Landed
+    {
+        slot : u64, vote_account : u32, voted_slots : Vec<u64>
+    },
+
+    Failed
+    {
+        slot : u64, vote_account : u32, error_kind : u32
+    }
+}
+
+// An input whose start was read by detect, with that start put back in front of it
+pub type Detected<R> = std::io::Chain<std::io::Cursor<Vec<u8>>, R>;
+
+// Reads as much of the start of the input as it takes to tell whether it is a vote stream (rather than text vote
+// data), since one read of a pipe may give fewer bytes than that.  Returns whether it is, and the input with what was
+// read put back in front of it.
+pub fn detect<R : BufRead>(mut input : R) -> std::io::Result<(bool, Detected<R>)>
+{
+    let mut start = Vec::with_capacity(MAGIC.len());
+
+    while start.len() < MAGIC.len() {
+        let available = match input.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e)
+        };
+        if available.is_empty() {
+            break;
+        }
+        let read = available.len().min(MAGIC.len() - start.len());
+        start.extend_from_slice(&available[..read]);
+        input.consume(read);
+    }
+
+    Ok((start.starts_with(MAGIC), std::io::Cursor::new(start).chain(input)))
+}
+
+pub struct Writer<W : Write>
+{
+    out : W,
+
+    vote_accounts : HashMap<[u8; 32], u32>,
+
+    error_kinds : HashMap<String, u32>,
+
+    previous_slot : u64
+}
+
+impl<W : Write> Writer<W>
+{
+    // Starts a vote stream, writing its header
+    pub fn new(
+        mut out : W,
+        header : &StreamHeader
+    ) -> std::io::Result<Self>
+    {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        write_string(&mut out, &header.cluster)?;
+        write_varint(&mut out, header.epoch)?;
+        write_varint(&mut out, header.first_slot)?;
+        write_varint(&mut out, header.last_slot)?;
+        write_string(&mut out, &header.tool_version)?;
+
+        Ok(Writer {
+            out,
+            vote_accounts : HashMap::new(),
+            error_kinds : HashMap::new(),
+            previous_slot : header.first_slot
+        })
+    }
+
+    // Continues a vote stream that was partly written, whose contents so far are read from existing, appending to out
+    pub fn resume(
+        out : W,
+        existing : impl Read
+    ) -> Result<Self, String>
+    {
+        let mut reader = Reader::new(existing)?;
+
+        while reader.next_record()?.is_some() {}
+
+        Ok(Writer {
+            out,
+            vote_accounts : reader.vote_accounts.iter().enumerate().map(|(i, key)| (*key, i as u32)).collect(),
+            error_kinds : reader.error_kinds.iter().enumerate().map(|(i, kind)| (kind.clone(), i as u32)).collect(),
+            previous_slot : reader.previous_slot
+        })
+    }
+
+    pub fn write_landed(
+        &mut self,
+        slot : u64,
+        vote_account : &[u8; 32],
+        voted_slots : &[u64]
+    ) -> std::io::Result<()>
+    {
+        let vote_account = self.vote_account(vote_account)?;
+
+        self.out.write_all(&[LANDED])?;
+        self.write_slot(slot)?;
+        write_varint(&mut self.out, vote_account as u64)?;
+        write_varint(&mut self.out, voted_slots.len() as u64)?;
+
+        let mut previous = slot;
+
+        for (i, voted_slot) in voted_slots.iter().enumerate() {
+            if i == 0 {
+                write_varint(&mut self.out, zigzag(slot.wrapping_sub(*voted_slot)))?;
+            }
+            else {
+                write_varint(&mut self.out, zigzag(voted_slot.wrapping_sub(previous)))?;
+            }
+            previous = *voted_slot;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_failed(
+        &mut self,
+        slot : u64,
+        vote_account : &[u8; 32],
+        error_kind : &str
+    ) -> std::io::Result<()>
+    {
+        let vote_account = self.vote_account(vote_account)?;
+
+        let error_kind = match self.error_kinds.get(error_kind) {
+            Some(error_kind) => *error_kind,
+            None => {
+                let number = self.error_kinds.len() as u32;
+                self.out.write_all(&[ERROR_KIND])?;
+                write_string(&mut self.out, error_kind)?;
+                self.error_kinds.insert(error_kind.to_string(), number);
+                number
+            }
+        };
+
+        self.out.write_all(&[FAILED])?;
+        self.write_slot(slot)?;
+        write_varint(&mut self.out, vote_account as u64)?;
+        write_varint(&mut self.out, error_kind as u64)
+    }
+
+    // Writes a vote line, whose vote account must be a base58 pubkey; the header must already have been written
+    pub fn write_line(
+        &mut self,
+        line : &VoteLine
+    ) -> Result<(), String>
+    {
+        let result = match line {
+            VoteLine::Header(_) => return Err("vote stream already has a header".to_string()),
+            VoteLine::Landed { slot, vote_account, voted_slots } => {
+                self.write_landed(*slot, &parse_vote_account(vote_account)?, voted_slots)
+            },
+            VoteLine::Failed { slot, vote_account, error_kind } => {
+                self.write_failed(*slot, &parse_vote_account(vote_account)?, error_kind)
+            },
+        };
+
+        result.map_err(|e| format!("Error writing vote stream: {}", e))
+    }
+
+    pub fn get_mut(&mut self) -> &mut W
+    {
+        &mut self.out
+    }
+
+    fn vote_account(
+        &mut self,
+        vote_account : &[u8; 32]
+    ) -> std::io::Result<u32>
+    {
+        if let Some(number) = self.vote_accounts.get(vote_account) {
+            return Ok(*number);
+        }
+
+        let number = self.vote_accounts.len() as u32;
+
+        self.out.write_all(&[VOTE_ACCOUNT])?;
+        self.out.write_all(vote_account)?;
+        self.vote_accounts.insert(*vote_account, number);
+
+        Ok(number)
+    }
+
+    fn write_slot(
+        &mut self,
+        slot : u64
+    ) -> std::io::Result<()>
+    {
+        write_varint(&mut self.out, zigzag(slot.wrapping_sub(self.previous_slot)))?;
+
+        self.previous_slot = slot;
+
+        Ok(())
+    }
+}
+
+pub struct Reader<R : Read>
+{
+    input : R,
+
+    header : StreamHeader,
+
+    vote_accounts : Vec<[u8; 32]>,
+
+    // The base58 form of each vote account, made once each
+    vote_account_names : Vec<String>,
+
+    error_kinds : Vec<String>,
+
+    previous_slot : u64,
+
+    // Number of records read, for error messages
+    records : u64
+}
+
+impl<R : Read> Reader<R>
+{
+    // Starts reading a vote stream, reading its header
+    pub fn new(mut input : R) -> Result<Self, String>
+    {
+        let mut magic = [0_u8; 8];
+
+        input.read_exact(&mut magic).map_err(|e| format!("Invalid vote stream header: {}", e))?;
+
+        if &magic != MAGIC {
+            return Err("Invalid vote stream: wrong magic".to_string());
+        }
+
+        let header = (|| {
+            let version = read_byte(&mut input)?.ok_or("missing version")?;
+            if version != VERSION {
+                return Err(format!("unsupported version {} (only {} is supported)", version, VERSION));
+            }
+            Ok(StreamHeader {
+                cluster : read_string(&mut input)?,
+                epoch : read_varint(&mut input)?,
+                first_slot : read_varint(&mut input)?,
+                last_slot : read_varint(&mut input)?,
+                tool_version : read_string(&mut input)?
+            })
+        })()
+        .map_err(|e : String| format!("Invalid vote stream header: {}", e))?;
+
+        Ok(Reader {
+            input,
+            previous_slot : header.first_slot,
+            header,
+            vote_accounts : vec![],
+            vote_account_names : vec![],
+            error_kinds : vec![],
+            records : 0
+        })
+    }
+
+    pub fn header(&self) -> &StreamHeader
+    {
+        &self.header
+    }
+
+    // Reads the next landed or failed record, or None at the end of the stream
+    pub fn next_record(&mut self) -> Result<Option<Record>, String>
+    {
+        loop {
+            self.records += 1;
+            let tag = match read_byte(&mut self.input) {
+                Ok(Some(tag)) => tag,
+                Ok(None) => return Ok(None),
+                Err(e) => return Err(self.invalid(e))
+            };
+            match self.read_record(tag) {
+                Ok(Some(record)) => return Ok(Some(record)),
+                Ok(None) => continue,
+                Err(e) => return Err(self.invalid(e))
+            }
+        }
+    }
+
+    pub fn vote_account(
+        &self,
+        number : u32
+    ) -> &[u8; 32]
+    {
+        &self.vote_accounts[number as usize]
+    }
+
+    // The base58 form of a vote account
+    pub fn vote_account_name(
+        &self,
+        number : u32
+    ) -> &str
+    {
+        &self.vote_account_names[number as usize]
+    }
+
+    pub fn error_kind(
+        &self,
+        number : u32
+    ) -> &str
+    {
+        &self.error_kinds[number as usize]
+    }
+
+    // Reads the record with the given tag, which is None for records that give vote accounts and error kinds
+    fn read_record(
+        &mut self,
+        tag : u8
+    ) -> Result<Option<Record>, String>
+    {
+        match tag {
+            VOTE_ACCOUNT => {
+                let mut vote_account = [0_u8; 32];
+                self.input.read_exact(&mut vote_account).map_err(|e| format!("truncated vote account: {}", e))?;
+                self.vote_accounts.push(vote_account);
+                self.vote_account_names.push(solana_sdk::pubkey::Pubkey::new_from_array(vote_account).to_string());
+                Ok(None)
+            },
+            ERROR_KIND => {
+                let error_kind = read_string(&mut self.input)?;
+                self.error_kinds.push(error_kind);
+                Ok(None)
+            },
+            LANDED => {
+                let slot = self.read_slot()?;
+                let vote_account = self.read_vote_account()?;
+                let count = read_varint(&mut self.input)?;
+                let mut voted_slots = Vec::with_capacity(count.min(64) as usize);
+                let mut previous = slot;
+                for i in 0..count {
+                    let difference = unzigzag(read_varint(&mut self.input)?);
+                    let voted_slot =
+                        if i == 0 { slot.wrapping_sub(difference) } else { previous.wrapping_add(difference) };
+                    voted_slots.push(voted_slot);
+                    previous = voted_slot;
+                }
+                Ok(Some(Record::Landed { slot, vote_account, voted_slots }))
+            },
+            FAILED => {
+                let slot = self.read_slot()?;
+                let vote_account = self.read_vote_account()?;
+                let error_kind = read_varint(&mut self.input)?;
+                if error_kind >= (self.error_kinds.len() as u64) {
+                    return Err(format!("error kind {} was not given", error_kind));
+                }
+                Ok(Some(Record::Failed { slot, vote_account, error_kind : error_kind as u32 }))
+            },
+            _ => Err(format!("unknown tag {}", tag))
+        }
+    }
+
+    fn read_slot(&mut self) -> Result<u64, String>
+    {
+        let slot = self.previous_slot.wrapping_add(unzigzag(read_varint(&mut self.input)?));
+
+        self.previous_slot = slot;
+
+        Ok(slot)
+    }
+
+    fn read_vote_account(&mut self) -> Result<u32, String>
+    {
+        let vote_account = read_varint(&mut self.input)?;
+
+        if vote_account >= (self.vote_accounts.len() as u64) {
+            return Err(format!("vote account {} was not given", vote_account));
+        }
+
+        Ok(vote_account as u32)
+    }
+
+    fn invalid(
+        &self,
+        e : String
+    ) -> String
+    {
+        format!("Invalid vote stream record {}: {}", self.records, e)
+    }
+}
+
+// Reads a vote stream as vote lines, starting with its header, so that it can be used wherever text vote data is read
+pub fn read_lines<R : Read>(mut reader : Reader<R>) -> impl Iterator<Item = Result<VoteLine, String>>
+{
+    let header = VoteLine::Header(EpochHeader {
+        epoch : reader.header.epoch,
+        first_slot : reader.header.first_slot,
+        last_slot : reader.header.last_slot
+    });
+
+    let mut failed = false;
+
+    std::iter::once(Ok(header)).chain(std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        match reader.next_record() {
+            Ok(Some(Record::Landed { slot, vote_account, voted_slots })) => Some(Ok(VoteLine::Landed {
+                slot,
+                vote_account : reader.vote_account_name(vote_account).to_string(),
+                voted_slots
+            })),
+            Ok(Some(Record::Failed { slot, vote_account, error_kind })) => Some(Ok(VoteLine::Failed {
+                slot,
+                vote_account : reader.vote_account_name(vote_account).to_string(),
+                error_kind : reader.error_kind(error_kind).to_string()
+            })),
+            Ok(None) => None,
+            Err(e) => {
+                failed = true;
+                Some(Err(e))
+            }
+        }
+    }))
+}
+
+// The 32 bytes of a base58 vote account
+pub fn parse_vote_account(vote_account : &str) -> Result<[u8; 32], String>
+{
+    vote_account
+        .parse::<solana_sdk::pubkey::Pubkey>()
+        .map(|pubkey| pubkey.to_bytes())
+        .map_err(|e| format!("Invalid vote account {}: {}", vote_account, e))
+}
+
+fn zigzag(difference : u64) -> u64
+{
+    let difference = difference as i64;
+
+    ((difference << 1) ^ (difference >> 63)) as u64
+}
+
+fn unzigzag(value : u64) -> u64
+{
+    ((value >> 1) as i64 ^ -((value & 1) as i64)) as u64
+}
+
+fn write_varint(
+    out : &mut impl Write,
+    mut value : u64
+) -> std::io::Result<()>
+{
+    let mut bytes = [0_u8; 10];
+
+    let mut length = 0;
+
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes[length] = byte;
+            length += 1;
+            break;
+        }
+        bytes[length] = byte | 0x80;
+        length += 1;
+    }
+
+    out.write_all(&bytes[..length])
+}
+
+fn write_string(
+    out : &mut impl Write,
+    value : &str
+) -> std::io::Result<()>
+{
+    write_varint(out, value.len() as u64)?;
+
+    out.write_all(value.as_bytes())
+}
+
+// Reads a byte, or None at the end of input
+fn read_byte(input : &mut impl Read) -> Result<Option<u8>, String>
+{
+    let mut byte = [0_u8; 1];
+
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.to_string())
+        }
+    }
+}
+
+fn read_varint(input : &mut impl Read) -> Result<u64, String>
+{
+    let mut value = 0_u64;
+
+    let mut shift = 0;
+
+    loop {
+        let byte = read_byte(input)?.ok_or("truncated varint")?;
+        if shift > 63 {
+            return Err("varint is too long".to_string());
+        }
+        value |= ((byte & 0x7f) as u64) << shift;
+        if (byte & 0x80) == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_string(input : &mut impl Read) -> Result<String, String>
+{
+    let length = read_varint(input)?;
+
+    let mut bytes = vec![];
+
+    input.take(length).read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+
+    if (bytes.len() as u64) != length {
+        return Err("truncated string".to_string());
+    }
+
+    String::from_utf8(bytes).map_err(|e| format!("invalid string: {}", e))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::records::Record as _;
+
+    const ACCOUNT_A : [u8; 32] = [1_u8; 32];
+
+    const ACCOUNT_B : [u8; 32] = [2_u8; 32];
+
+    fn header() -> StreamHeader
+    {
+        StreamHeader {
+            cluster : "testnet".to_string(),
+            epoch : 600,
+            first_slot : 259200000,
+            last_slot : 259631999,
+            tool_version : "0.1.0".to_string()
+        }
+    }
+
+    // A record with its vote account and error kind given by value, for comparing
+    #[derive(Debug, PartialEq)]
+    enum Read
+    {
+        Landed(u64, [u8; 32], Vec<u64>),
+
+        Failed(u64, [u8; 32], String)
+    }
+
+    fn read_all(stream : &[u8]) -> Result<Vec<Read>, String>
+    {
+        let mut reader = Reader::new(stream)?;
+
+        let mut records = vec![];
+
+        while let Some(record) = reader.next_record()? {
+            records.push(match record {
+                Record::Landed { slot, vote_account, voted_slots } => {
+                    Read::Landed(slot, *reader.vote_account(vote_account), voted_slots)
+                },
+                Record::Failed { slot, vote_account, error_kind } => {
+                    Read::Failed(slot, *reader.vote_account(vote_account), reader.error_kind(error_kind).to_string())
+                },
+            });
+        }
+
+        Ok(records)
+    }
+
+    fn count(
+        haystack : &[u8],
+        needle : &[u8]
+    ) -> usize
+    {
+        haystack.windows(needle.len()).filter(|window| *window == needle).count()
+    }
+
+    #[test]
+    fn round_trips_landed_and_failed_records()
+    {
+        let mut writer = Writer::new(vec![], &header()).unwrap();
+
+        writer.write_landed(259200005, &ACCOUNT_A, &[259200003, 259200004]).unwrap();
+        // Slots may go backwards, and a voted slot may be above the transaction's slot, which are negative deltas
+        writer.write_landed(259200001, &ACCOUNT_B, &[259200002, 259200001, 259199990]).unwrap();
+        writer.write_landed(259200010, &ACCOUNT_A, &[]).unwrap();
+        writer.write_failed(259200011, &ACCOUNT_B, "LockoutConflict").unwrap();
+        writer.write_failed(259200012, &ACCOUNT_A, "BlockhashNotFound").unwrap();
+        writer.write_failed(259200013, &ACCOUNT_A, "LockoutConflict").unwrap();
+
+        let stream = writer.get_mut().clone();
+
+        // Each vote account and error kind is given only once
+        assert_eq!(count(&stream, &ACCOUNT_A), 1);
+        assert_eq!(count(&stream, b"LockoutConflict"), 1);
+
+        let mut reader = Reader::new(stream.as_slice()).unwrap();
+
+        assert_eq!(reader.header().cluster, "testnet");
+        assert_eq!(
+            (reader.header().epoch, reader.header().first_slot, reader.header().last_slot),
+            (600, 259200000, 259631999)
+        );
+        assert_eq!(reader.header().tool_version, "0.1.0");
+        assert!(reader.next_record().unwrap().is_some());
+        assert_eq!(reader.vote_account_name(0), solana_sdk::pubkey::Pubkey::new_from_array(ACCOUNT_A).to_string());
+
+        assert_eq!(read_all(&stream).unwrap(), vec![
+            Read::Landed(259200005, ACCOUNT_A, vec![259200003, 259200004]),
+            Read::Landed(259200001, ACCOUNT_B, vec![259200002, 259200001, 259199990]),
+            Read::Landed(259200010, ACCOUNT_A, vec![]),
+            Read::Failed(259200011, ACCOUNT_B, "LockoutConflict".to_string()),
+            Read::Failed(259200012, ACCOUNT_A, "BlockhashNotFound".to_string()),
+            Read::Failed(259200013, ACCOUNT_A, "LockoutConflict".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn round_trips_vote_lines()
+    {
+        let a = solana_sdk::pubkey::Pubkey::new_from_array(ACCOUNT_A).to_string();
+
+        let lines = [format!("259200005 {} 259200003 259200004", a), format!("259200006 {} !LockoutConflict", a)];
+
+        let mut writer = Writer::new(vec![], &header()).unwrap();
+
+        for line in &lines {
+            writer.write_line(&VoteLine::parse(line).unwrap()).unwrap();
+        }
+
+        assert!(writer.write_line(&VoteLine::parse("259200007 notbase58! 1").unwrap()).is_err());
+
+        let read : Vec<String> = read_lines(Reader::new(writer.get_mut().as_slice()).unwrap())
+            .map(|line| line.unwrap().to_string())
+            .collect();
+
+        assert_eq!(read, vec!["# 600 259200000 259631999".to_string(), lines[0].clone(), lines[1].clone()]);
+    }
+
+    #[test]
+    fn resumes_after_partial_write()
+    {
+        let mut writer = Writer::new(vec![], &header()).unwrap();
+
+        writer.write_landed(259200005, &ACCOUNT_A, &[259200003, 259200004]).unwrap();
+        writer.write_failed(259200006, &ACCOUNT_A, "LockoutConflict").unwrap();
+
+        // What was written before the checkpoint is kept, and writing carries on from there
+        let existing = writer.get_mut().clone();
+
+        let mut resumed = Writer::resume(existing.clone(), existing.as_slice()).unwrap();
+
+        resumed.write_landed(259200007, &ACCOUNT_A, &[259200006]).unwrap();
+        resumed.write_landed(259200008, &ACCOUNT_B, &[259200007]).unwrap();
+        resumed.write_failed(259200009, &ACCOUNT_B, "LockoutConflict").unwrap();
+
+        let stream = resumed.get_mut().clone();
+
+        // Vote accounts and error kinds given before resuming are not given again
+        assert_eq!(count(&stream, &ACCOUNT_A), 1);
+        assert_eq!(count(&stream, b"LockoutConflict"), 1);
+
+        assert_eq!(read_all(&stream).unwrap(), vec![
+            Read::Landed(259200005, ACCOUNT_A, vec![259200003, 259200004]),
+            Read::Failed(259200006, ACCOUNT_A, "LockoutConflict".to_string()),
+            Read::Landed(259200007, ACCOUNT_A, vec![259200006]),
+            Read::Landed(259200008, ACCOUNT_B, vec![259200007]),
+            Read::Failed(259200009, ACCOUNT_B, "LockoutConflict".to_string()),
+        ]);
+
+        // A stream cut off in the middle of a record can't be resumed
+        assert!(Writer::resume(vec![], &existing[..(existing.len() - 1)]).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_and_invalid_streams()
+    {
+        let mut writer = Writer::new(vec![], &header()).unwrap();
+
+        let header_length = writer.get_mut().len();
+
+        writer.write_failed(259200006, &ACCOUNT_A, "LockoutConflict").unwrap();
+
+        let stream = writer.get_mut().clone();
+
+        // Cut off in the header's cluster string, and in the header's epoch varint (whose high bit says more follows)
+        assert!(Reader::new(&stream[..12]).err().unwrap().contains("truncated string"));
+
+        let mut truncated_varint = stream[..(MAGIC.len() + 1)].to_vec();
+        truncated_varint.push(0);
+        truncated_varint.push(0x80);
+
+        assert!(Reader::new(truncated_varint.as_slice()).err().unwrap().contains("truncated varint"));
+
+        // Cut off in the error kind string, which follows the vote account record
+        let error_kind_start = header_length + 1 + 32;
+
+        let e = read_all(&stream[..(error_kind_start + 5)]).err().unwrap();
+
+        assert!(e.contains("truncated string"), "{}", e);
+
+        // Cut off in the varint of a landed record's slot
+        let mut writer = Writer::new(vec![], &header()).unwrap();
+        writer.write_landed(259300000, &ACCOUNT_A, &[259299999]).unwrap();
+        let stream = writer.get_mut().clone();
+        let slot_start = header_length + 1 + 32 + 1;
+
+        let e = read_all(&stream[..(slot_start + 1)]).err().unwrap();
+
+        assert!(e.contains("truncated varint"), "{}", e);
+
+        // A landed record of a vote account that was never given
+        let mut unknown = Writer::new(vec![], &header()).unwrap().get_mut().clone();
+        unknown.extend_from_slice(&[LANDED, 0, 5, 0]);
+
+        let e = read_all(&unknown).err().unwrap();
+
+        assert!(e.contains("vote account 5 was not given"), "{}", e);
+
+        // A failed record of an error kind that was never given
+        let mut unknown = Writer::new(vec![], &header()).unwrap().get_mut().clone();
+        unknown.push(VOTE_ACCOUNT);
+        unknown.extend_from_slice(&ACCOUNT_A);
+        unknown.extend_from_slice(&[FAILED, 0, 0, 3]);
+
+        let e = read_all(&unknown).err().unwrap();
+
+        assert!(e.contains("error kind 3 was not given"), "{}", e);
+
+        assert!(Reader::new(&b"NOTVOTES\x01"[..]).err().unwrap().contains("wrong magic"));
+    }
+
+    #[test]
+    fn varints_and_zigzag_round_trip()
+    {
+        for value in [0, 1, 127, 128, 16383, 16384, u32::MAX as u64, u64::MAX] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, value).unwrap();
+            assert_eq!(read_varint(&mut bytes.as_slice()).unwrap(), value);
+        }
+
+        for difference in [0_i64, -1, 1, -2, 2, i64::MIN, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(difference as u64)) as i64, difference);
+        }
+
+        assert_eq!((zigzag(0), zigzag(-1_i64 as u64), zigzag(1), zigzag(-2_i64 as u64)), (0, 1, 2, 3));
+    }
+
+    #[test]
+    fn detects_vote_stream_from_short_reads()
+    {
+        // Gives one byte per read, as a pipe may
+        struct Trickle<'a>(&'a [u8]);
+
+        impl std::io::Read for Trickle<'_>
+        {
+            fn read(
+                &mut self,
+                buffer : &mut [u8]
+            ) -> std::io::Result<usize>
+            {
+                if self.0.is_empty() || buffer.is_empty() {
+                    return Ok(0);
+                }
+                buffer[0] = self.0[0];
+                self.0 = &self.0[1..];
+                Ok(1)
+            }
+        }
+
+        let mut writer = Writer::new(vec![], &header()).unwrap();
+
+        writer.write_landed(259200005, &ACCOUNT_A, &[259200003]).unwrap();
+
+        let stream = writer.get_mut().clone();
+
+        let (is_vote_stream, input) = detect(std::io::BufReader::with_capacity(1, Trickle(&stream))).unwrap();
+
+        assert!(is_vote_stream);
+
+        let mut read = vec![];
+
+        std::io::Read::read_to_end(&mut { input }, &mut read).unwrap();
+
+        assert_eq!(read, stream);
+
+        let (is_vote_stream, _) = detect(std::io::BufReader::with_capacity(1, Trickle(b"# 600 1 2\n"))).unwrap();
+
+        assert!(!is_vote_stream);
+
+        let (is_vote_stream, _) = detect(&b"VOTE"[..]).unwrap();
+
+        assert!(!is_vote_stream);
+    }
+}